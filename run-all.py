@@ -60,7 +60,8 @@ def run_runner(name: str, cmd: list[str], cwd: Path) -> RunnerReport:
             runner=name,
             results=results,
             passed=data["summary"]["passed"],
-            failed=data["summary"]["failed"],
+            # Errored evals (provider/infra problems) count as failures here
+            failed=data["summary"]["failed"] + data["summary"].get("errors", 0),
             skipped=data["summary"]["skipped"],
             total=data["summary"]["total"],
         )
@@ -108,7 +109,7 @@ def run_runner(name: str, cmd: list[str], cwd: Path) -> RunnerReport:
 
 def status_symbol(status: str) -> str:
     """Return emoji/symbol for status."""
-    return {"pass": "✅", "fail": "❌", "skip": "⏭️"}.get(status, "❓")
+    return {"pass": "✅", "fail": "❌", "skip": "⏭️", "error": "⚠️"}.get(status, "❓")
 
 
 def status_color(status: str) -> str:
@@ -117,6 +118,7 @@ def status_color(status: str) -> str:
         "pass": "\033[32m",  # Green
         "fail": "\033[31m",  # Red
         "skip": "\033[33m",  # Yellow
+        "error": "\033[35m",  # Magenta
     }.get(status, "")
 
 
@@ -327,7 +329,7 @@ def generate_html(reports: list[RunnerReport], output_path: Path):
         for runner in runners:
             result = results_by_id.get((runner, eval_id))
             if result:
-                symbol = {"pass": "✅", "fail": "❌", "skip": "⏭️"}.get(result.status, "❓")
+                symbol = {"pass": "✅", "fail": "❌", "skip": "⏭️", "error": "⚠️"}.get(result.status, "❓")
                 css_class = result.status
                 title = result.reason or ""
                 html += f'                <td class="status {css_class}" title="{title}">{symbol}</td>\n'
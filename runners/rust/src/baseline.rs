@@ -0,0 +1,175 @@
+// ABOUTME: Compares a run against a report saved earlier with --format json.
+// ABOUTME: --baseline prints what newly broke or got fixed and fails the run only on regressions.
+
+use crate::report::EvalRunResult;
+use crate::EvalResult;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Statuses from a saved JSON report, by eval id
+pub struct Baseline {
+    path: PathBuf,
+    statuses: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct SavedReport {
+    results: Vec<SavedResult>,
+}
+
+#[derive(Deserialize)]
+struct SavedResult {
+    id: String,
+    status: String,
+}
+
+/// How this run differs from the baseline, each list in eval id order
+pub struct BaselineDiff<'a> {
+    baseline: &'a Path,
+    /// Passed in the baseline, failed or errored now
+    regressions: Vec<&'a EvalRunResult>,
+    /// Failed or errored in the baseline, passed now
+    fixes: Vec<&'a EvalRunResult>,
+    /// Run now but absent from the baseline
+    added: Vec<&'a EvalRunResult>,
+    /// In the baseline but no longer defined in any eval file
+    removed: Vec<&'a str>,
+    /// Still defined but not run this time, e.g. filtered out or cut off by --fail-fast
+    not_run: usize,
+}
+
+impl Baseline {
+    /// Read a report written by `--format json`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let report: SavedReport = serde_json::from_str(&content).with_context(|| {
+            format!("Baseline {} is not a --format json report", path.display())
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            statuses: report
+                .results
+                .into_iter()
+                .map(|r| (r.id, r.status))
+                .collect(),
+        })
+    }
+
+    /// Diff `results` against the baseline; `defined` holds every eval id in the loaded files
+    ///
+    /// Only a pass turning into a fail or error is a regression. Skips on either side are
+    /// neither regressions nor fixes, since they usually mean a missing key or server.
+    pub fn diff<'a>(
+        &'a self,
+        results: &'a [EvalRunResult],
+        defined: &HashSet<String>,
+    ) -> BaselineDiff<'a> {
+        let mut diff = BaselineDiff {
+            baseline: &self.path,
+            regressions: Vec::new(),
+            fixes: Vec::new(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            not_run: 0,
+        };
+        for r in results {
+            match self.statuses.get(&r.id).map(String::as_str) {
+                None => diff.added.push(r),
+                Some("pass") if r.result.is_failure() => diff.regressions.push(r),
+                Some("fail" | "error") if matches!(r.result, EvalResult::Pass) => {
+                    diff.fixes.push(r)
+                }
+                Some(_) => {}
+            }
+        }
+        let ran: HashSet<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        for id in self.statuses.keys() {
+            if !defined.contains(id) {
+                diff.removed.push(id);
+            } else if !ran.contains(id.as_str()) {
+                diff.not_run += 1;
+            }
+        }
+        for list in [&mut diff.regressions, &mut diff.fixes, &mut diff.added] {
+            list.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        diff
+    }
+}
+
+impl BaselineDiff<'_> {
+    pub fn has_regressions(&self) -> bool {
+        !self.regressions.is_empty()
+    }
+
+    /// The comparison for stderr: a headline, then one section per non-empty list
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "\n{} {}: {} regressions, {} fixed, {} added, {} removed",
+            "Baseline".bold(),
+            self.baseline.display(),
+            self.regressions.len(),
+            self.fixes.len(),
+            self.added.len(),
+            self.removed.len()
+        );
+        if self.not_run > 0 {
+            let note = format!("({} baseline evals not run this time)", self.not_run);
+            out.push_str(&format!(" {}", note.dimmed()));
+        }
+
+        if !self.regressions.is_empty() {
+            out.push_str(&format!(
+                "\n\n{}",
+                "REGRESSIONS (passed in baseline, failing now)".red().bold()
+            ));
+            for r in &self.regressions {
+                let reason = match &r.result {
+                    EvalResult::Fail(reason) | EvalResult::Error(reason) => reason.as_str(),
+                    _ => "",
+                };
+                out.push_str(&format!(
+                    "\n  {} - {}\n       {}",
+                    r.id,
+                    r.name,
+                    reason.dimmed()
+                ));
+            }
+        }
+        if !self.fixes.is_empty() {
+            out.push_str(&format!(
+                "\n\n{}",
+                "Fixed (failing in baseline, passing now)".green().bold()
+            ));
+            for r in &self.fixes {
+                out.push_str(&format!("\n  {} - {}", r.id, r.name));
+            }
+        }
+        if !self.added.is_empty() {
+            out.push_str(&format!("\n\n{}", "Added (not in baseline)".bold()));
+            for r in &self.added {
+                let status = match r.result {
+                    EvalResult::Pass => "pass".green(),
+                    EvalResult::Fail(_) => "fail".red(),
+                    EvalResult::Skip(_) => "skip".yellow(),
+                    EvalResult::Error(_) => "error".magenta(),
+                };
+                out.push_str(&format!("\n  {} - {} ({})", r.id, r.name, status));
+            }
+        }
+        if !self.removed.is_empty() {
+            out.push_str(&format!(
+                "\n\n{}",
+                "Removed (in baseline, no longer defined)".bold()
+            ));
+            for id in &self.removed {
+                out.push_str(&format!("\n  {}", id));
+            }
+        }
+        out
+    }
+}
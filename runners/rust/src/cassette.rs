@@ -0,0 +1,173 @@
+// ABOUTME: Record/replay layer for LLM calls, so agent and llm evals can rerun without the network.
+// ABOUTME: Interactions are stored per eval as JSON, keyed on provider, model and a hash of the messages.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use colored::Colorize;
+use mux::llm::{LlmClient, LlmError, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Modes selectable with --cassette-mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CassetteMode {
+    /// Call the real provider and save every response
+    Record,
+    /// Serve saved responses and never touch the network
+    Replay,
+}
+
+/// One saved request/response pair
+#[derive(Debug, Serialize, Deserialize)]
+struct Interaction {
+    provider: String,
+    model: String,
+    messages_hash: String,
+    response: serde_json::Value,
+}
+
+/// Guards read-modify-write of cassette files when evals record concurrently
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Replay misses per eval id, collected until the eval's result is reported
+static MISSES: Mutex<BTreeMap<String, Vec<String>>> = Mutex::new(BTreeMap::new());
+
+/// Wraps a provider client; records through `inner` when set, otherwise replays
+pub struct CassetteClient {
+    dir: PathBuf,
+    provider: String,
+    inner: Option<Arc<dyn LlmClient>>,
+}
+
+impl CassetteClient {
+    pub fn record(dir: &Path, provider: &str, inner: Arc<dyn LlmClient>) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            provider: provider.to_string(),
+            inner: Some(inner),
+        }
+    }
+
+    pub fn replay(dir: &Path, provider: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            provider: provider.to_string(),
+            inner: None,
+        }
+    }
+
+    fn path(&self, eval_id: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", crate::eval_file_name(eval_id)))
+    }
+
+    /// Save an interaction, replacing an earlier one for the same request
+    fn store(&self, path: &Path, interaction: Interaction) -> Result<()> {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        let mut interactions = load(path)?;
+        interactions.retain(|i| {
+            (&i.provider, &i.model, &i.messages_hash)
+                != (
+                    &interaction.provider,
+                    &interaction.model,
+                    &interaction.messages_hash,
+                )
+        });
+        interactions.push(interaction);
+        let json = serde_json::to_string_pretty(&interactions)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+
+    fn find(&self, path: &Path, model: &str, hash: &str) -> Result<Option<Response>> {
+        let found = load(path)?
+            .into_iter()
+            .find(|i| i.provider == self.provider && i.model == model && i.messages_hash == hash);
+        match found {
+            Some(i) => Ok(Some(serde_json::from_value(i.response)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Interactions saved in a cassette file; a missing file has none
+fn load(path: &Path) -> Result<Vec<Interaction>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid cassette {}", path.display()))
+}
+
+/// FNV-1a over the serialized messages; stable across runs and Rust versions
+fn messages_hash(request: &Request) -> String {
+    let serialized = serde_json::to_string(&request.messages).unwrap_or_default();
+    let hash = serialized.bytes().fold(0xcbf29ce484222325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Replay misses recorded for an eval, clearing them
+pub fn take_misses(eval_id: &str) -> Vec<String> {
+    MISSES.lock().unwrap().remove(eval_id).unwrap_or_default()
+}
+
+#[async_trait]
+impl LlmClient for CassetteClient {
+    async fn create_message(&self, request: &Request) -> Result<Response, LlmError> {
+        let eval_id = crate::current_eval_id();
+        let path = self.path(&eval_id);
+        let hash = messages_hash(request);
+
+        if let Some(inner) = &self.inner {
+            let response = inner.create_message(request).await?;
+            let stored = serde_json::to_value(&response)
+                .map_err(anyhow::Error::from)
+                .and_then(|response| {
+                    self.store(
+                        &path,
+                        Interaction {
+                            provider: self.provider.clone(),
+                            model: request.model.clone(),
+                            messages_hash: hash,
+                            response,
+                        },
+                    )
+                });
+            if let Err(e) = stored {
+                eprintln!(
+                    "{} failed to record cassette for {}: {:#}",
+                    "warning:".yellow().bold(),
+                    eval_id,
+                    e
+                );
+            }
+            return Ok(response);
+        }
+
+        // A miss fails the eval once it finishes; the empty response just lets the handler return
+        let miss = match self.find(&path, &request.model, &hash) {
+            Ok(Some(response)) => return Ok(response),
+            Ok(None) => format!(
+                "No cassette in {} matches {} {} (messages {})",
+                path.display(),
+                self.provider,
+                request.model,
+                hash
+            ),
+            Err(e) => format!("{:#}", e),
+        };
+        MISSES
+            .lock()
+            .unwrap()
+            .entry(eval_id)
+            .or_default()
+            .push(miss);
+        Ok(Response::default())
+    }
+}
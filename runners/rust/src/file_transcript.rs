@@ -0,0 +1,65 @@
+// ABOUTME: Disk-backed TranscriptStore used by the transcript evals alongside mux's in-memory store.
+// ABOUTME: Keeps one pretty-printed JSON file of messages per agent id in a directory.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use mux::agent::TranscriptStore;
+use mux::llm::Message;
+use std::path::{Path, PathBuf};
+
+/// Persists each agent's transcript as `<dir>/<agent-id>.json`
+pub struct FileTranscriptStore {
+    dir: PathBuf,
+}
+
+impl FileTranscriptStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// File holding `agent_id`'s transcript; ids are sanitized so they can't escape `dir`
+    pub fn path(&self, agent_id: &str) -> PathBuf {
+        let name: String = agent_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[async_trait]
+impl TranscriptStore for FileTranscriptStore {
+    async fn save(&self, agent_id: &str, messages: &[Message]) -> Result<()> {
+        let path = self.path(agent_id);
+        let json = serde_json::to_string_pretty(messages)?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        tokio::fs::write(&path, json)
+            .await
+            .with_context(|| format!("Failed to write transcript {}", path.display()))
+    }
+
+    async fn load(&self, agent_id: &str) -> Result<Option<Vec<Message>>> {
+        let path = self.path(agent_id);
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read transcript {}", path.display()))
+            }
+        };
+        let messages = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid transcript {}", path.display()))?;
+        Ok(Some(messages))
+    }
+}
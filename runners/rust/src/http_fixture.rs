@@ -0,0 +1,150 @@
+// ABOUTME: Tiny local HTTP server with canned routes, so networked tool evals don't need the internet.
+// ABOUTME: Tool evals declare routes in `given.http_routes` and reach them through `{fixture}` URLs.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Placeholder in eval inputs replaced by the fixture's base URL, e.g. `{fixture}/ok`
+pub const PLACEHOLDER: &str = "{fixture}";
+
+/// Largest request head the fixture reads before answering anyway
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+struct Route {
+    status: u16,
+    body: String,
+    delay: Duration,
+}
+
+/// A running fixture; the server stops when this is dropped
+pub struct Fixture {
+    url: String,
+    task: JoinHandle<()>,
+}
+
+impl Fixture {
+    /// Base URL without a trailing slash, e.g. `http://127.0.0.1:41237`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serve `routes` on an ephemeral localhost port
+///
+/// `routes` maps a path to `{"status": 200, "body": "...", "delay_ms": 0}`, every field
+/// optional. Unknown paths get a 404.
+pub async fn serve(routes: &Value) -> Result<Fixture> {
+    let routes = routes
+        .as_object()
+        .context("given.http_routes must be an object of path -> route")?;
+    let routes: HashMap<String, Route> = routes
+        .iter()
+        .map(|(path, route)| {
+            let route = Route {
+                status: route.get("status").and_then(|s| s.as_u64()).unwrap_or(200) as u16,
+                body: route
+                    .get("body")
+                    .and_then(|b| b.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                delay: Duration::from_millis(
+                    route.get("delay_ms").and_then(|d| d.as_u64()).unwrap_or(0),
+                ),
+            };
+            (path.clone(), route)
+        })
+        .collect();
+    let routes = Arc::new(routes);
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .context("Failed to bind the HTTP fixture")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let task = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let routes = Arc::clone(&routes);
+            tokio::spawn(async move {
+                let path = match read_path(&mut stream).await {
+                    Some(path) => path,
+                    None => return,
+                };
+                let (status, body, delay) = match routes.get(&path) {
+                    Some(route) => (route.status, route.body.as_str(), route.delay),
+                    None => (404, "not found", Duration::ZERO),
+                };
+                tokio::time::sleep(delay).await;
+                let response = format!(
+                    "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    reason(status),
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+
+    Ok(Fixture { url, task })
+}
+
+/// Path of the request line, read up to the end of the request head
+async fn read_path(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let target = head.lines().next()?.split_whitespace().nth(1)?;
+    // Routes match on the path alone, so `/ok?x=1` is served by `/ok`
+    Some(target.split('?').next().unwrap_or(target).to_string())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
+
+/// `value` with every string's `{fixture}` replaced by `url`
+pub fn substitute(value: &Value, url: &str) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.replace(PLACEHOLDER, url)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, url)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute(v, url)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
@@ -1,38 +1,98 @@
 // ABOUTME: Rust eval runner for mux-rs library.
 // ABOUTME: Executes language-agnostic eval definitions against the Rust implementation.
 
+mod baseline;
+mod cassette;
+mod file_transcript;
+mod http_fixture;
+mod mock_llm;
+mod mock_mcp;
+mod ollama;
+mod report;
+mod tools;
+mod trace;
+
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use clap::Parser;
+use cassette::{CassetteClient, CassetteMode};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use file_transcript::FileTranscriptStore;
+use futures::StreamExt;
 use mux::agent::{MemoryTranscriptStore, TranscriptStore};
 use mux::hook::{Hook, HookAction, HookEvent, HookRegistry};
-use mux::llm::{AnthropicClient, ContentBlock, LlmClient, Message, OpenAIClient, Request, Role};
+use mux::llm::{
+    AnthropicClient, ContentBlock, LlmClient, Message, OpenAIClient, Request, Response, Role,
+    StreamEvent, ToolDefinition,
+};
+use mux::mcp::{McpClient, McpServerConfig, McpTransport};
 use mux::tool::{Registry, Tool, ToolResult};
+use notify::{EventKind, RecursiveMode, Watcher};
+use ollama::OllamaClient;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use report::{build_formatter, sort_results, EvalOutcome, EvalRunResult, OutputFormat, SortBy};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 #[derive(Parser)]
 #[command(name = "mux-eval-runner")]
 #[command(about = "Run mux evals against the Rust implementation")]
 struct Args {
-    /// Path to evals directory or specific .jsonl file
-    #[arg(short, long, default_value = "../../evals")]
-    evals: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Filter by category (tools, hooks, agent, etc.)
-    #[arg(short, long)]
-    category: Option<String>,
+    /// Eval files, directories (searched recursively for .jsonl) or globs; repeat or comma-separate
+    #[arg(short, long, default_value = "../../evals", value_delimiter = ',')]
+    evals: Vec<PathBuf>,
+
+    /// Filter by category (tools, hooks, agent, etc.); repeat or comma-separate to run several
+    #[arg(short, long, value_delimiter = ',')]
+    category: Vec<String>,
 
     /// Filter by specific eval ID
     #[arg(short, long)]
     id: Option<String>,
 
+    /// Only run evals carrying this tag; repeat to require several
+    #[arg(long, value_name = "TAG")]
+    tag: Vec<String>,
+
+    /// Skip evals carrying this tag (e.g. slow), even ones matching --tag; repeatable
+    #[arg(long, value_name = "TAG")]
+    exclude_tag: Vec<String>,
+
+    /// Print the evals the filters select (id, category, name, required key; JSON with --format json) and
+    /// check their shapes, without running them
+    #[arg(long)]
+    list: bool,
+
+    /// Seed for everything the runner randomizes (e.g. --shuffle); printed at startup when omitted
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Run evals in a random order derived from --seed, to catch evals that depend on each other
+    #[arg(long)]
+    shuffle: bool,
+
+    /// Re-run the selected evals whenever an eval file under --evals changes, until Ctrl-C
+    #[arg(long, conflicts_with = "list")]
+    watch: bool,
+
+    /// Check the selected evals' given/when/then shapes, report every problem and exit without running
+    #[arg(long)]
+    validate: bool,
+
+    /// Skip the shape check that otherwise runs before every run
+    #[arg(long, conflicts_with = "validate")]
+    no_validate: bool,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
@@ -41,493 +101,2919 @@ struct Args {
     #[arg(long)]
     failures_only: bool,
 
-    /// Output results as JSON
+    /// Include skipped evals in json/junit/markdown/csv/tap output (the default)
+    #[arg(long, overrides_with = "no_include_skipped")]
+    include_skipped: bool,
+
+    /// Leave skipped evals out of json/junit/markdown/csv/tap output; human output is unaffected
+    #[arg(long, overrides_with = "include_skipped")]
+    no_include_skipped: bool,
+
+    /// Output format for results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Order reported results; per-eval lines are then printed after the run completes
+    #[arg(long, value_enum)]
+    sort_by: Option<SortBy>,
+
+    /// Output results as JSON (shorthand for --format json)
     #[arg(long)]
     json: bool,
 
-    /// Judge model for evaluating agent outputs (default: gpt-5-mini)
-    #[arg(long, default_value = "gpt-5-mini")]
-    judge_model: String,
-}
+    /// Also write a JUnit XML report to this file, whatever --format is
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
 
-// ============================================================================
-// Judge Agent - Uses LLM to evaluate if agent completed task correctly
-// ============================================================================
+    /// Also write pass/fail counts per category, wall-clock time and failing eval ids as JSON to this file
+    #[arg(long, value_name = "PATH")]
+    summary_json: Option<PathBuf>,
 
-struct Judge {
-    client: Arc<dyn LlmClient>,
-    model: String,
-}
+    /// Judge model for evaluating agent outputs (default: the judge provider's default model)
+    #[arg(long)]
+    judge_model: Option<String>,
 
-impl Judge {
-    fn new(client: Arc<dyn LlmClient>, model: String) -> Self {
-        Self { client, model }
-    }
+    /// Model agent and subagent evals run on unless they set given.model; the provider follows the name
+    #[arg(long, value_name = "MODEL", default_value = DEFAULT_AGENT_MODEL)]
+    agent_model: String,
 
-    async fn evaluate(
-        &self,
-        task: &str,
-        agent_output: &str,
-        criteria: &str,
-    ) -> Result<(bool, String)> {
-        let prompt = format!(
-            r#"You are an eval judge. Evaluate if the agent completed the task correctly.
+    /// Answer agent, llm and judge requests from this fixtures file instead of real providers
+    #[arg(long, value_name = "FIXTURES")]
+    mock_llm: Option<PathBuf>,
 
-TASK: {}
+    /// Record LLM responses to, or replay them from, per-eval files in this directory
+    #[arg(long, value_name = "DIR")]
+    cassette: Option<PathBuf>,
 
-AGENT OUTPUT:
-{}
+    /// Whether --cassette records real responses or replays saved ones offline
+    #[arg(long, value_enum, default_value_t = CassetteMode::Replay)]
+    cassette_mode: CassetteMode,
 
-EVALUATION CRITERIA: {}
+    /// Shorthand for --cassette DIR --cassette-mode record
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["cassette", "replay"])]
+    record: Option<PathBuf>,
 
-Respond with EXACTLY this format (no markdown, no extra text):
-VERDICT: PASS or FAIL
-REASON: One sentence explanation
+    /// Shorthand for --cassette DIR --cassette-mode replay
+    #[arg(long, value_name = "DIR", conflicts_with = "cassette")]
+    replay: Option<PathBuf>,
 
-Example:
-VERDICT: PASS
-REASON: The agent correctly completed the requested task."#,
-            task, agent_output, criteria
-        );
+    /// Write every LLM request and response (judge calls included) to <DIR>/<eval-id>.json
+    #[arg(long, value_name = "DIR")]
+    trace_dir: Option<PathBuf>,
 
-        let request = Request {
-            model: self.model.clone(),
-            messages: vec![Message {
-                role: Role::User,
-                content: vec![ContentBlock::Text { text: prompt }],
-            }],
-            max_tokens: Some(200),
-            ..Default::default()
-        };
+    /// Grade with several judges and take the majority verdict (comma-separated models; a tie
+    /// is settled by --judge-tie)
+    #[arg(
+        long,
+        value_name = "MODELS",
+        value_delimiter = ',',
+        conflicts_with_all = ["judge_model", "judge_provider"]
+    )]
+    judges: Vec<String>,
 
-        let response = self.client.create_message(&request).await?;
+    /// Ask each judge N times at a nonzero temperature and take the majority (a tie is settled
+    /// by --judge-tie)
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    judge_samples: usize,
 
-        // Parse the judge's response
-        let text = response
-            .content
-            .iter()
-            .filter_map(|b| {
-                if let ContentBlock::Text { text } = b {
-                    Some(text.as_str())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("");
+    /// How an even split of judge votes is decided
+    #[arg(long, value_enum, default_value_t = JudgeTie::FailClosed)]
+    judge_tie: JudgeTie,
 
-        let passed = text.contains("VERDICT: PASS");
-        let reason = text
-            .lines()
-            .find(|l| l.starts_with("REASON:"))
-            .map(|l| l.trim_start_matches("REASON:").trim().to_string())
-            .unwrap_or_else(|| "No reason provided".to_string());
+    /// Token limit for each judge reply; raise it for longer reasons on complex criteria
+    #[arg(long, value_name = "N", default_value_t = JUDGE_MAX_TOKENS)]
+    judge_max_tokens: u32,
 
-        Ok((passed, reason))
-    }
-}
+    /// Sampling temperature for judge calls (default 0, or 0.7 with --judge-samples above 1)
+    #[arg(long, value_name = "T")]
+    judge_temperature: Option<f32>,
 
-fn create_judge() -> Option<Judge> {
-    let api_key = std::env::var("OPENAI_API_KEY").ok()?;
-    let client = OpenAIClient::new(api_key);
-    Some(Judge::new(Arc::new(client), "gpt-5-mini".to_string()))
-}
+    /// Provider serving the judge (default: inferred from --judge-model, else openai)
+    #[arg(long, value_enum)]
+    judge_provider: Option<JudgeProvider>,
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Eval {
-    id: String,
-    name: String,
-    description: String,
-    category: String,
-    #[serde(default)]
-    provider: Option<String>,
-    #[serde(default)]
-    requires_key: Option<String>,
-    given: serde_json::Value,
-    when: serde_json::Value,
-    then: serde_json::Value,
-}
+    /// Language agent outputs are expected in, so the judge grades them natively (e.g. "Japanese")
+    #[arg(long, value_name = "LANGUAGE")]
+    judge_language: Option<String>,
 
-#[derive(Debug)]
-enum EvalResult {
-    Pass,
-    Fail(String),
-    Skip(String),
-}
+    /// Enable `then.deterministic` probes (requests already default to temperature 0)
+    #[arg(long)]
+    deterministic: bool,
 
-/// JSON output format for each eval result
-#[derive(Serialize)]
-struct JsonEvalResult {
-    id: String,
-    name: String,
-    category: String,
-    status: String,
-    reason: Option<String>,
-}
+    /// Stop launching new evals once this many have failed; the rest are reported as skipped
+    #[arg(long, value_name = "N")]
+    max_failures: Option<usize>,
 
-/// JSON output format for the full report
-#[derive(Serialize)]
-struct JsonReport {
-    runner: String,
-    results: Vec<JsonEvalResult>,
-    summary: JsonSummary,
-}
+    /// Re-run a failing eval up to this many times; a later pass is reported as flaky
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
 
-#[derive(Serialize)]
-struct JsonSummary {
-    passed: usize,
-    failed: usize,
-    skipped: usize,
-    total: usize,
-}
+    /// Fail the run when more than this many evals are skipped, even if none failed
+    #[arg(long, value_name = "N")]
+    max_skips: Option<usize>,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Load .env file from current directory or parent directories
-    let _ = dotenvy::dotenv();
+    /// Compare with a report saved by --format json; only evals that passed there and fail now
+    /// fail the run
+    #[arg(long, value_name = "RESULTS_JSON")]
+    baseline: Option<PathBuf>,
 
-    let args = Args::parse();
+    /// Stop at the first failing eval (skips and errors don't count), cancelling evals still
+    /// running; the summary then only covers evals that finished before it
+    #[arg(long)]
+    fail_fast: bool,
 
-    let evals = load_evals(&args.evals, args.category.as_deref(), args.id.as_deref())?;
+    /// Fail any eval that runs longer than this many seconds (an eval's `timeout_secs` wins)
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
 
-    // Create judge if API key is available
-    let judge = create_judge();
-    if !args.json && judge.is_some() {
-        eprintln!("{}", "Judge agent enabled (using GPT-5-mini)".dimmed());
-    }
+    /// Backend for transcript evals that don't set `given.store`
+    #[arg(long, value_enum, default_value = "memory")]
+    transcript_store: TranscriptBackend,
 
-    if !args.json {
-        eprintln!("\n{} {} evals\n", "Running".bold().cyan(), evals.len());
-    }
+    /// Run up to N evals at once; results are still reported in input order
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
 
-    let mut passed = 0;
-    let mut failed = 0;
-    let mut skipped = 0;
-    let mut json_results = Vec::new();
+    /// Keep captured artifacts (judge prompt/response) for passing evals, not just failures
+    #[arg(long)]
+    capture_on_pass: bool,
 
-    for eval in &evals {
-        let result = run_eval(eval, args.verbose, judge.as_ref()).await;
+    /// Capture thinking/reasoning blocks from agent responses (judging still uses final text)
+    #[arg(long)]
+    capture_thinking: bool,
 
-        let (status, reason) = match &result {
-            EvalResult::Pass => {
-                passed += 1;
-                ("pass".to_string(), None)
-            }
-            EvalResult::Fail(r) => {
-                failed += 1;
-                ("fail".to_string(), Some(r.clone()))
-            }
-            EvalResult::Skip(r) => {
-                skipped += 1;
-                ("skip".to_string(), Some(r.clone()))
-            }
-        };
+    /// Only run evals whose defining lines changed since this git ref
+    #[arg(long, value_name = "GIT_REF")]
+    since: Option<String>,
 
-        if args.json {
-            json_results.push(JsonEvalResult {
-                id: eval.id.clone(),
-                name: eval.name.clone(),
-                category: eval.category.clone(),
-                status,
-                reason,
-            });
-        } else {
-            match &result {
-                EvalResult::Pass => {
-                    if !args.failures_only {
-                        println!("{} {} - {}", "PASS".green().bold(), eval.id, eval.name);
-                    }
-                }
-                EvalResult::Fail(reason) => {
-                    println!(
-                        "{} {} - {}\n       {}",
-                        "FAIL".red().bold(),
-                        eval.id,
-                        eval.name,
-                        reason.dimmed()
-                    );
-                }
-                EvalResult::Skip(reason) => {
-                    if !args.failures_only {
-                        println!(
-                            "{} {} - {}\n       {}",
-                            "SKIP".yellow().bold(),
-                            eval.id,
-                            eval.name,
-                            reason.dimmed()
-                        );
-                    }
-                }
-            }
-        }
-    }
+    /// Run only this CI shard of the selected evals, as INDEX/TOTAL with INDEX counted from 0
+    #[arg(long, value_name = "INDEX/TOTAL")]
+    shard: Option<Shard>,
 
-    if args.json {
-        let report = JsonReport {
-            runner: "rust".to_string(),
-            results: json_results,
-            summary: JsonSummary {
-                passed,
-                failed,
-                skipped,
-                total: evals.len(),
-            },
-        };
-        println!("{}", serde_json::to_string_pretty(&report)?);
-    } else {
-        println!(
-            "\n{}: {} passed, {} failed, {} skipped\n",
-            "Results".bold(),
-            passed.to_string().green(),
-            if failed > 0 {
-                failed.to_string().red()
-            } else {
-                failed.to_string().normal()
-            },
-            skipped.to_string().yellow()
-        );
-    }
+    /// Warn instead of aborting on malformed lines or invalid evals (skipped) or unknown categories (kept)
+    #[arg(long)]
+    lenient: bool,
 
-    if failed > 0 {
-        std::process::exit(1);
-    }
+    /// Minimum weighted score (0.0-1.0) for evals graded on `then.subgoals`
+    #[arg(long, default_value_t = 1.0)]
+    min_score: f64,
 
-    Ok(())
-}
+    /// After the run, retry evals that errored (provider/infra problems), keeping genuine failures
+    #[arg(long)]
+    rerun_errors: bool,
 
-fn load_evals(
-    path: &PathBuf,
-    category_filter: Option<&str>,
-    id_filter: Option<&str>,
-) -> Result<Vec<Eval>> {
-    let mut evals = Vec::new();
+    /// Fail evals whose `when.params`/`when.provider_overrides` use unsupported fields instead of ignoring them
+    #[arg(long)]
+    strict_params: bool,
 
-    let files = if path.is_dir() {
-        std::fs::read_dir(path)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|p| p.extension().map(|e| e == "jsonl").unwrap_or(false))
-            .collect()
-    } else {
-        vec![path.clone()]
-    };
+    /// Print a breakdown of time spent loading, in each category, and in provider/judge calls
+    #[arg(long)]
+    profile: bool,
 
-    for file_path in files {
-        let file = File::open(&file_path)
-            .with_context(|| format!("Failed to open {}", file_path.display()))?;
-        let reader = BufReader::new(file);
+    /// Also write the --profile breakdown as folded stacks (for inferno/flamegraph) to this file
+    #[arg(long, value_name = "PATH")]
+    profile_folded: Option<PathBuf>,
+}
 
-        for (line_num, line) in reader.lines().enumerate() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
+#[derive(Subcommand)]
+enum Command {
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Serve a mock MCP server over stdio (spawned by the mcp evals)
+    #[command(hide = true)]
+    MockMcpServer {
+        /// Tool names to expose
+        #[arg(long, value_delimiter = ',')]
+        tools: Vec<String>,
+    },
+}
 
-            let eval: Eval = serde_json::from_str(&line).with_context(|| {
-                format!(
-                    "Failed to parse line {} in {}",
-                    line_num + 1,
-                    file_path.display()
-                )
-            })?;
+/// Providers selectable with --judge-provider
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum JudgeProvider {
+    Openai,
+    Anthropic,
+    Gemini,
+}
 
-            // Apply filters
-            if let Some(cat) = category_filter {
-                if eval.category != cat {
-                    continue;
-                }
-            }
-            if let Some(id) = id_filter {
-                if eval.id != id {
-                    continue;
-                }
-            }
+/// Policies selectable with --judge-tie for an even split of judge votes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum JudgeTie {
+    /// The eval fails
+    #[default]
+    FailClosed,
+    /// The eval passes
+    FailOpen,
+    /// The panel's first judge casts one more, deciding vote
+    Escalate,
+}
 
-            evals.push(eval);
+impl JudgeTie {
+    fn name(self) -> &'static str {
+        match self {
+            JudgeTie::FailClosed => "fail-closed",
+            JudgeTie::FailOpen => "fail-open",
+            JudgeTie::Escalate => "escalate",
         }
     }
-
-    Ok(evals)
 }
 
-async fn run_eval(eval: &Eval, verbose: bool, judge: Option<&Judge>) -> EvalResult {
-    // Check for required API keys
-    if let Some(key) = &eval.requires_key {
-        if std::env::var(key).is_err() {
-            return EvalResult::Skip(format!("{} not set", key));
+impl JudgeProvider {
+    fn name(self) -> &'static str {
+        match self {
+            JudgeProvider::Openai => "openai",
+            JudgeProvider::Anthropic => "anthropic",
+            JudgeProvider::Gemini => "gemini",
         }
     }
 
-    if verbose {
-        println!("  given: {:?}", eval.given);
-        println!("  when: {:?}", eval.when);
-        println!("  then: {:?}", eval.then);
+    /// Model used when --judge-model isn't given
+    fn default_model(self) -> &'static str {
+        match self {
+            JudgeProvider::Openai => "gpt-5-mini",
+            JudgeProvider::Anthropic => "claude-sonnet-4-20250514",
+            JudgeProvider::Gemini => "gemini-2.5-flash",
+        }
     }
+}
 
-    // Dispatch based on category
-    match eval.category.as_str() {
-        "tools" => run_tool_eval(eval).await,
-        "hooks" => run_hook_eval(eval).await,
-        "agent" => run_agent_eval(eval, judge).await,
-        "subagent" => run_subagent_eval(eval, judge).await,
-        "transcript" => run_transcript_eval(eval).await,
-        "mcp" => run_mcp_eval(eval).await,
-        "llm" => run_llm_eval(eval, judge).await,
-        _ => EvalResult::Skip(format!("Unknown category: {}", eval.category)),
+/// Transcript store backends selectable with --transcript-store or `given.store`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum TranscriptBackend {
+    /// mux's in-memory store
+    Memory,
+    /// The runner's FileTranscriptStore, in a temp dir removed after the eval
+    File,
+}
+
+/// One slice of the eval list from --shard; every shard of the same run is disjoint
+#[derive(Clone, Copy, Debug)]
+struct Shard {
+    index: usize,
+    total: usize,
+}
+
+impl std::str::FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, total) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected INDEX/TOTAL (e.g. 0/4), got \"{}\"", s))?;
+        let index: usize = index
+            .trim()
+            .parse()
+            .map_err(|_| format!("shard index \"{}\" is not a number", index))?;
+        let total: usize = total
+            .trim()
+            .parse()
+            .map_err(|_| format!("shard total \"{}\" is not a number", total))?;
+        if total == 0 {
+            return Err("shard total must be at least 1".to_string());
+        }
+        if index >= total {
+            return Err(format!(
+                "shard index {} must be less than the total {} (indexes start at 0)",
+                index, total
+            ));
+        }
+        Ok(Shard { index, total })
+    }
+}
+
+impl Shard {
+    /// Keep every eval whose position is `index` modulo `total`
+    ///
+    /// Positions come from the loaded order, so the same files give the same split on every machine.
+    fn select<T>(self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % self.total == self.index)
+            .map(|(_, item)| item)
+            .collect()
     }
 }
 
+/// Per-run settings shared by every eval handler
+#[derive(Clone, Copy)]
+struct RunOptions {
+    verbose: bool,
+    deterministic: bool,
+    capture_thinking: bool,
+    min_score: f64,
+    strict_params: bool,
+    timeout_secs: Option<u64>,
+    transcript_store: TranscriptBackend,
+}
+
 // ============================================================================
-// Test Tools
+// Provider Usage - Requests sent to each LLM provider over the whole run
 // ============================================================================
 
-struct AddTool;
+/// Requests sent to one provider; `judge` is the subset made while grading
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+struct ProviderRequests {
+    requests: usize,
+    judge: usize,
+}
 
-#[async_trait]
-impl Tool for AddTool {
-    fn name(&self) -> &str {
-        "add"
-    }
-    fn description(&self) -> &str {
-        "Adds two numbers"
-    }
-    fn schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "a": {"type": "number"},
-                "b": {"type": "number"}
-            },
-            "required": ["a", "b"]
-        })
-    }
-    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
-        let a = params["a"].as_f64().unwrap_or(0.0);
-        let b = params["b"].as_f64().unwrap_or(0.0);
-        Ok(ToolResult::text(format!("{}", a + b)))
+static PROVIDER_REQUESTS: Mutex<BTreeMap<String, ProviderRequests>> = Mutex::new(BTreeMap::new());
+
+/// Count a request as it is sent, so failed calls still show up
+fn record_provider_request(provider: &str, judge: bool) {
+    let mut usage = PROVIDER_REQUESTS.lock().unwrap();
+    let entry = usage.entry(provider.to_string()).or_default();
+    entry.requests += 1;
+    if judge {
+        entry.judge += 1;
     }
 }
 
-struct DivideTool;
+/// Tokens and request count for one model, with an estimated cost when its price is known
+#[derive(Debug, Clone, Serialize)]
+struct ModelUsage {
+    provider: String,
+    model: String,
+    requests: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+static MODEL_USAGE: Mutex<BTreeMap<(String, String), (usize, TokenUsage)>> =
+    Mutex::new(BTreeMap::new());
 
-#[async_trait]
-impl Tool for DivideTool {
-    fn name(&self) -> &str {
-        "divide"
-    }
-    fn description(&self) -> &str {
-        "Divides two numbers"
-    }
-    fn schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "a": {"type": "number"},
-                "b": {"type": "number"}
-            },
-            "required": ["a", "b"]
-        })
-    }
-    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
-        let a = params["a"].as_f64().unwrap_or(0.0);
-        let b = params["b"].as_f64().unwrap_or(0.0);
-        if b == 0.0 {
-            return Err(anyhow::anyhow!("Division by zero"));
+/// USD per million input and output tokens, matched on model-name prefix (longest first)
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash", 0.1, 0.4),
+];
+
+/// Send one request, counting it against the provider and metering its tokens per model
+async fn send_request(
+    client: &Arc<dyn LlmClient>,
+    provider: &str,
+    request: &Request,
+    judge: bool,
+) -> Result<Response> {
+    record_provider_request(provider, judge);
+    let kind = if judge { "judge" } else { "provider" };
+
+    // Sample greedily unless the request or the eval's `when.temperature` says otherwise.
+    // TODO(seed): also send run_seed() once mux's Request has a seed field; OpenAI and Gemini
+    // accept one, but today only the runner's own Ollama client can pass it on.
+    let sampled;
+    let request = match request.temperature {
+        Some(_) => request,
+        None => {
+            sampled = Request {
+                temperature: Some(sampling_temperature(judge)),
+                ..request.clone()
+            };
+            &sampled
         }
-        Ok(ToolResult::text(format!("{}", a / b)))
-    }
+    };
+    let outcome = timed_call(kind, client.create_message(request)).await;
+    trace::record(
+        provider,
+        judge,
+        request,
+        outcome.as_ref().map_err(|e| e.to_string()),
+    );
+    let response = outcome?;
+    meter_usage(provider, &request.model, &response);
+    Ok(response)
 }
 
-struct GreetTool;
-
-#[async_trait]
-impl Tool for GreetTool {
-    fn name(&self) -> &str {
-        "greet"
-    }
-    fn description(&self) -> &str {
-        "Returns greeting"
-    }
-    fn schema(&self) -> serde_json::Value {
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {"type": "string"}
-            },
-            "required": ["name"]
-        })
-    }
-    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
-        let name = params["name"].as_str().unwrap_or("World");
-        Ok(ToolResult::text(format!("Hello, {}!", name)))
-    }
+/// Count one request against `model` and add the response's tokens to its totals
+fn meter_usage(provider: &str, model: &str, response: &Response) {
+    let mut usage = MODEL_USAGE.lock().unwrap();
+    let entry = usage
+        .entry((provider.to_string(), model.to_string()))
+        .or_default();
+    entry.0 += 1;
+    entry.1 += TokenUsage::of(response);
 }
 
-struct GetInfoTool;
+/// Temperature for requests that don't set one, so repeated runs sample the same way
+const DEFAULT_TEMPERATURE: f32 = 0.0;
 
-#[async_trait]
-impl Tool for GetInfoTool {
-    fn name(&self) -> &str {
-        "get_info"
-    }
-    fn description(&self) -> &str {
-        "Returns info object"
-    }
-    fn schema(&self) -> serde_json::Value {
-        serde_json::json!({"type": "object", "properties": {}})
-    }
-    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
-        Ok(ToolResult::text(
-            serde_json::json!({"version": "1.0", "name": "mux"}).to_string(),
-        ))
-    }
+/// Temperature for a request that doesn't set one: the eval's `when.temperature`, else greedy
+///
+/// Judge calls ignore `when.temperature`; `--judge-temperature` sets theirs on the request.
+fn sampling_temperature(judge: bool) -> f32 {
+    let eval_temperature = if judge {
+        None
+    } else {
+        CURRENT_EVAL.try_with(|e| e.temperature).ok().flatten()
+    };
+    eval_temperature.unwrap_or(DEFAULT_TEMPERATURE)
 }
 
-struct CounterTool {
-    count: AtomicUsize,
+/// Dollar cost of `usage` on `model`, or None when the model has no known price
+fn cost_usd(model: &str, usage: TokenUsage) -> Option<f64> {
+    let (_, input, output) = MODEL_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())?;
+    Some((usage.prompt as f64 * input + usage.completion as f64 * output) / 1_000_000.0)
 }
 
-impl CounterTool {
-    fn new() -> Self {
-        Self {
-            count: AtomicUsize::new(0),
-        }
-    }
+/// Token totals per provider and model; cost is None when the price or the usage is unknown
+fn model_usage() -> Vec<ModelUsage> {
+    MODEL_USAGE
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((provider, model), (requests, usage))| {
+            let cost_usd = cost_usd(model, *usage).filter(|_| usage.total() > 0);
+            ModelUsage {
+                provider: provider.clone(),
+                model: model.clone(),
+                requests: *requests,
+                input_tokens: usage.prompt,
+                output_tokens: usage.completion,
+                cost_usd,
+            }
+        })
+        .collect()
 }
 
-#[async_trait]
-impl Tool for CounterTool {
-    fn name(&self) -> &str {
-        "counter"
-    }
-    fn description(&self) -> &str {
-        "Increments counter"
-    }
-    fn schema(&self) -> serde_json::Value {
-        serde_json::json!({"type": "object", "properties": {}})
-    }
-    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
-        let new_count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
-        Ok(ToolResult::text(format!("Count: {}", new_count)))
+/// Requests per provider so far; known providers are listed even when never hit
+fn provider_usage() -> BTreeMap<String, ProviderRequests> {
+    let mut usage = PROVIDER_REQUESTS.lock().unwrap().clone();
+    for provider in ["anthropic", "openai", "gemini"] {
+        usage.entry(provider.to_string()).or_default();
     }
+    usage
 }
 
 // ============================================================================
-// Tool Evals
+// Profile - Where the runner's own time goes, recorded only with --profile
 // ============================================================================
 
-async fn run_tool_eval(eval: &Eval) -> EvalResult {
-    let registry = Registry::new();
+/// Accumulated wall-clock time per folded stack, e.g. `eval;agent;judge`
+#[derive(Debug, Default)]
+struct Profile {
+    frames: BTreeMap<String, Duration>,
+}
 
-    // Register tools based on eval requirements
-    registry.register(AddTool).await;
-    registry.register(DivideTool).await;
-    registry.register(GreetTool).await;
-    registry.register(GetInfoTool).await;
+static PROFILE: Mutex<Option<Profile>> = Mutex::new(None);
 
-    match eval.id.as_str() {
-        "tool-001" => {
-            // tool_execution_basic - Add two numbers
+fn enable_profile() {
+    *PROFILE.lock().unwrap() = Some(Profile::default());
+}
+
+/// Add time to a stack; a no-op unless --profile is on
+fn record_timing(stack: &str, elapsed: Duration) {
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        *profile.frames.entry(stack.to_string()).or_default() += elapsed;
+    }
+}
+
+/// Add time spent in a provider or judge call to the current category
+fn record_call_timing(kind: &str, elapsed: Duration) {
+    let category = CURRENT_EVAL
+        .try_with(|e| e.category.clone())
+        .unwrap_or_else(|_| "unknown".to_string());
+    record_timing(&format!("eval;{};{}", category, kind), elapsed);
+}
+
+/// Await a provider (`kind = "provider"`) or judge (`kind = "judge"`) call, recording its time
+async fn timed_call<F: std::future::Future>(kind: &str, call: F) -> F::Output {
+    let started = Instant::now();
+    let output = call.await;
+    record_call_timing(kind, started.elapsed());
+    output
+}
+
+/// Recorded stacks with their self time (total minus time in child stacks)
+fn profile_self_times() -> Vec<(String, Duration)> {
+    let guard = PROFILE.lock().unwrap();
+    let frames = match guard.as_ref() {
+        Some(profile) => &profile.frames,
+        None => return Vec::new(),
+    };
+    frames
+        .iter()
+        .map(|(stack, total)| {
+            let children: Duration = frames
+                .iter()
+                .filter(|(other, _)| {
+                    other
+                        .strip_prefix(stack.as_str())
+                        .and_then(|rest| rest.strip_prefix(';'))
+                        .is_some_and(|rest| !rest.contains(';'))
+                })
+                .map(|(_, d)| *d)
+                .sum();
+            (stack.clone(), total.saturating_sub(children))
+        })
+        .collect()
+}
+
+/// Print the per-stack breakdown to stderr, as a share of the whole run
+fn print_profile(total: Duration) {
+    let guard = PROFILE.lock().unwrap();
+    let frames = match guard.as_ref() {
+        Some(profile) => &profile.frames,
+        None => return,
+    };
+    let width = frames.keys().map(|k| k.len()).max().unwrap_or(0);
+    eprintln!("\n{}", "Profile:".bold());
+    for (stack, elapsed) in frames {
+        let share = 100.0 * elapsed.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON);
+        eprintln!(
+            "  {:<width$}  {:>8}ms  {:>5.1}%",
+            stack,
+            elapsed.as_millis(),
+            share,
+            width = width
+        );
+    }
+    eprintln!(
+        "  {:<width$}  {:>8}ms",
+        "total",
+        total.as_millis(),
+        width = width
+    );
+}
+
+/// Write self times as folded stacks (`stack microseconds` per line)
+fn write_profile_folded(path: &Path) -> Result<()> {
+    let folded: String = profile_self_times()
+        .into_iter()
+        .filter(|(_, elapsed)| !elapsed.is_zero())
+        .map(|(stack, elapsed)| format!("{} {}\n", stack, elapsed.as_micros()))
+        .collect();
+    std::fs::write(path, folded)
+        .with_context(|| format!("Failed to write profile to {}", path.display()))
+}
+
+// ============================================================================
+// Judge Agent - Uses LLM to evaluate if agent completed task correctly
+// ============================================================================
+
+#[derive(Clone)]
+struct Judge {
+    client: Arc<dyn LlmClient>,
+    provider: &'static str,
+    model: String,
+    /// Language the agent output is expected in; None grades as English
+    language: Option<String>,
+    /// Re-prompts allowed when a response has no parseable verdict
+    judge_max_retries: usize,
+    /// Sampling temperature; None leaves the judge at the greedy default
+    temperature: Option<f32>,
+    /// Token limit for each judge reply
+    max_tokens: u32,
+}
+
+/// Default for `Judge::judge_max_retries`
+const JUDGE_MAX_RETRIES: usize = 2;
+
+/// Temperature for --judge-samples, so repeated samples can actually disagree
+const JUDGE_SAMPLE_TEMPERATURE: f32 = 0.7;
+
+/// Default for `Judge::max_tokens`; enough for a one-sentence reason
+const JUDGE_MAX_TOKENS: u32 = 200;
+
+/// `then.min_score` for rubric evals that don't set one, on the judge's 0-10 scale
+const RUBRIC_MIN_SCORE: f64 = 7.0;
+
+/// Passes the judge is less sure of than this are flagged in the output
+const LOW_CONFIDENCE_PASS: f64 = 0.6;
+
+/// The judge never produced a verdict, even after its retries
+#[derive(Debug)]
+struct UnparseableVerdict;
+
+impl std::fmt::Display for UnparseableVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "judge returned unparseable verdict")
+    }
+}
+
+impl std::error::Error for UnparseableVerdict {}
+
+impl Judge {
+    fn new(client: Arc<dyn LlmClient>, provider: &'static str, model: String) -> Self {
+        Self {
+            client,
+            provider,
+            model,
+            language: None,
+            judge_max_retries: JUDGE_MAX_RETRIES,
+            temperature: None,
+            max_tokens: JUDGE_MAX_TOKENS,
+        }
+    }
+
+    fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Grade the agent output; `tool_trace` lets the judge weigh how the answer was reached
+    async fn evaluate(
+        &self,
+        task: &str,
+        agent_output: &str,
+        criteria: &str,
+        tool_trace: Option<&str>,
+    ) -> Result<Verdict> {
+        let trace_section = match tool_trace {
+            Some(trace) => format!("\nTOOL CALLS MADE BY THE AGENT:\n{}\n", trace),
+            None => String::new(),
+        };
+        // Only the grading changes; the JSON keys and values stay fixed so parsing is unaffected
+        let language_section = match &self.language {
+            Some(language) => format!(
+                "\nThe agent output is expected to be in {0}. Grade it as a fluent {0} speaker would, \
+                 and do not penalize it for not being in English. Keep the JSON keys and the \
+                 pass/fail values in English exactly as shown below.\n",
+                language
+            ),
+            None => String::new(),
+        };
+
+        let prompt = format!(
+            r#"You are an eval judge. Evaluate if the agent completed the task correctly.
+{}
+TASK: {}
+
+AGENT OUTPUT:
+{}
+{}
+EVALUATION CRITERIA: {}
+
+Respond with ONLY this JSON object (no markdown, no extra text):
+{{"verdict": "pass" or "fail", "confidence": 0.0 to 1.0, "reason": "One sentence explanation"}}
+
+Example:
+{{"verdict": "pass", "confidence": 0.9, "reason": "The agent correctly completed the requested task."}}"#,
+            language_section, task, agent_output, trace_section, criteria
+        );
+
+        let mut messages = vec![Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text {
+                text: prompt.clone(),
+            }],
+        }];
+        let mut latency_ms = 0;
+        let mut attempts = 0;
+        let mut parse_retries = 0;
+        // Separate from parse_retries, so a reply that needed a parse retry can still be re-prompted
+        let mut echo_retried = false;
+
+        loop {
+            attempts += 1;
+            let request = Request {
+                model: self.model.clone(),
+                messages: messages.clone(),
+                max_tokens: Some(self.max_tokens),
+                temperature: self.temperature,
+                ..Default::default()
+            };
+
+            let started = Instant::now();
+            let response = send_request(&self.client, self.provider, &request, true).await?;
+            latency_ms += started.elapsed().as_millis() as u64;
+
+            // Parse the judge's response
+            let text = collect_text(&response.content);
+            let judgement = match parse_judgement(&text) {
+                Some(judgement) => judgement,
+                None if parse_retries < self.judge_max_retries => {
+                    parse_retries += 1;
+                    messages.push(Message {
+                        role: Role::Assistant,
+                        content: response.content,
+                    });
+                    messages.push(Message {
+                        role: Role::User,
+                        content: vec![ContentBlock::Text {
+                            text: "Your reply had no verdict. Reply again with only this JSON \
+                                   object and nothing else:\n{\"verdict\": \"pass\" or \"fail\", \
+                                   \"confidence\": 0.0 to 1.0, \"reason\": \"One sentence \
+                                   explanation\"}"
+                                .to_string(),
+                        }],
+                    });
+                    continue;
+                }
+                None => return Err(UnparseableVerdict.into()),
+            };
+            let reason = judgement
+                .reason
+                .unwrap_or_else(|| "No reason provided".to_string());
+
+            // A reason that just restates the criteria means the judge didn't actually look
+            if echoes_criteria(&reason, criteria) {
+                if !echo_retried {
+                    echo_retried = true;
+                    messages.push(Message {
+                        role: Role::Assistant,
+                        content: response.content,
+                    });
+                    messages.push(Message {
+                        role: Role::User,
+                        content: vec![ContentBlock::Text {
+                            text:
+                                "Your reason only restates the evaluation criteria. Explain what \
+                                   in the agent output meets or misses them, using the same format."
+                                    .to_string(),
+                        }],
+                    });
+                    continue;
+                }
+                eprintln!(
+                    "{} judge reason still restates the criteria after a re-prompt: {}",
+                    "warning:".yellow().bold(),
+                    reason
+                );
+            }
+
+            return Ok(Verdict {
+                passed: judgement.passed,
+                confidence: judgement.confidence,
+                reason,
+                exchanges: vec![JudgeExchange {
+                    model: self.model.clone(),
+                    prompt,
+                    response: text,
+                    latency_ms,
+                    attempts,
+                }],
+            });
+        }
+    }
+
+    /// Score the agent output 0-10 against a rubric, for evals graded on quality rather than pass/fail
+    async fn score(
+        &self,
+        task: &str,
+        agent_output: &str,
+        rubric: &str,
+        tool_trace: Option<&str>,
+    ) -> Result<RubricScore> {
+        let trace_section = match tool_trace {
+            Some(trace) => format!("\nTOOL CALLS MADE BY THE AGENT:\n{}\n", trace),
+            None => String::new(),
+        };
+        let language_section = match &self.language {
+            Some(language) => format!(
+                "\nThe agent output is expected to be in {0}. Score it as a fluent {0} speaker would, \
+                 and do not penalize it for not being in English. Keep the SCORE and REASON labels \
+                 in English exactly as shown below.\n",
+                language
+            ),
+            None => String::new(),
+        };
+
+        let prompt = format!(
+            r#"You are an eval judge. Score how well the agent output meets the rubric.
+{}
+TASK: {}
+
+AGENT OUTPUT:
+{}
+{}
+RUBRIC: {}
+
+Score from 0 (does not meet the rubric at all) to 10 (meets it fully).
+Respond with ONLY these two lines:
+SCORE: <integer from 0 to 10>
+REASON: <one sentence explanation>"#,
+            language_section, task, agent_output, trace_section, rubric
+        );
+
+        let mut messages = vec![Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text {
+                text: prompt.clone(),
+            }],
+        }];
+        let mut latency_ms = 0;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let request = Request {
+                model: self.model.clone(),
+                messages: messages.clone(),
+                max_tokens: Some(self.max_tokens),
+                temperature: self.temperature,
+                ..Default::default()
+            };
+
+            let started = Instant::now();
+            let response = send_request(&self.client, self.provider, &request, true).await?;
+            latency_ms += started.elapsed().as_millis() as u64;
+
+            let text = collect_text(&response.content);
+            let score = match parse_score(&text) {
+                Some(score) => score,
+                None if (attempts as usize) <= self.judge_max_retries => {
+                    messages.push(Message {
+                        role: Role::Assistant,
+                        content: response.content,
+                    });
+                    messages.push(Message {
+                        role: Role::User,
+                        content: vec![ContentBlock::Text {
+                            text: "Your reply had no score. Reply again with only these two \
+                                   lines:\nSCORE: <integer from 0 to 10>\nREASON: <one sentence \
+                                   explanation>"
+                                .to_string(),
+                        }],
+                    });
+                    continue;
+                }
+                None => return Err(UnparseableVerdict.into()),
+            };
+            let reason = text
+                .lines()
+                .find_map(|l| labelled_value(l, "REASON"))
+                .filter(|r| !r.is_empty())
+                .unwrap_or("No reason provided")
+                .to_string();
+
+            return Ok(RubricScore {
+                score,
+                reason,
+                exchanges: vec![JudgeExchange {
+                    model: self.model.clone(),
+                    prompt,
+                    response: text,
+                    latency_ms,
+                    attempts,
+                }],
+            });
+        }
+    }
+}
+
+/// The judge's 0-10 rubric score, tolerating `7/10` or `7.` after the label
+fn parse_score(text: &str) -> Option<u8> {
+    let value = text.lines().find_map(|l| labelled_value(l, "SCORE"))?;
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse::<u8>().ok().filter(|s| *s <= 10)
+}
+
+/// A rubric score plus the exchanges that produced it
+struct RubricScore {
+    score: u8,
+    reason: String,
+    exchanges: Vec<JudgeExchange>,
+}
+
+/// The JSON object the judge is asked to reply with
+#[derive(Deserialize)]
+struct JudgeReply {
+    verdict: String,
+    #[serde(default)]
+    confidence: Option<f64>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// A judge reply reduced to what grading needs
+struct Judgement {
+    passed: bool,
+    confidence: Option<f64>,
+    reason: Option<String>,
+}
+
+/// Read the judge's reply as JSON, falling back to `VERDICT:`/`REASON:` lines
+///
+/// The JSON object may be wrapped in prose or a code fence. Only when it is missing or
+/// malformed are the labelled lines used, so "FAIL because it is not a PASS" can't pass.
+fn parse_judgement(text: &str) -> Option<Judgement> {
+    let json = text
+        .find('{')
+        .zip(text.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| serde_json::from_str::<JudgeReply>(&text[start..=end]).ok());
+    if let Some(reply) = json {
+        let passed = match reply.verdict.trim().to_ascii_lowercase().as_str() {
+            "pass" => Some(true),
+            "fail" => Some(false),
+            _ => None,
+        };
+        if let Some(passed) = passed {
+            return Some(Judgement {
+                passed,
+                confidence: reply
+                    .confidence
+                    .filter(|c| c.is_finite())
+                    .map(|c| c.clamp(0.0, 1.0)),
+                reason: reply.reason.filter(|r| !r.trim().is_empty()),
+            });
+        }
+    }
+
+    let passed = parse_verdict(text)?;
+    let label = |name: &str| text.lines().find_map(|l| labelled_value(l, name));
+    Some(Judgement {
+        passed,
+        confidence: label("CONFIDENCE")
+            .and_then(|c| c.parse::<f64>().ok())
+            .filter(|c| c.is_finite())
+            .map(|c| c.clamp(0.0, 1.0)),
+        reason: label("REASON").map(str::to_string),
+    })
+}
+
+/// Read PASS/FAIL from the judge's VERDICT line, if it has one
+fn parse_verdict(text: &str) -> Option<bool> {
+    text.lines().find_map(|line| {
+        let value = labelled_value(line, "VERDICT")?;
+        let word = value
+            .split_whitespace()
+            .next()?
+            .trim_matches(|c: char| !c.is_ascii_alphabetic());
+        if word.eq_ignore_ascii_case("pass") {
+            Some(true)
+        } else if word.eq_ignore_ascii_case("fail") {
+            Some(false)
+        } else {
+            None
+        }
+    })
+}
+
+/// The value of a `LABEL: value` line, tolerating indentation, any case and markdown
+/// emphasis such as `**Verdict:** PASS`
+fn labelled_value<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let decoration = |c: char| c.is_whitespace() || matches!(c, '*' | '_' | '#' | '`' | '>' | '-');
+    let (name, value) = line.trim_matches(decoration).split_once(':')?;
+    name.trim_matches(decoration)
+        .eq_ignore_ascii_case(label)
+        .then(|| value.trim_matches(decoration))
+}
+
+/// Whether a judge reason is a near-verbatim copy of the criteria it was given
+fn echoes_criteria(reason: &str, criteria: &str) -> bool {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (reason, criteria) = (words(reason), words(criteria));
+    if reason.is_empty() || criteria.is_empty() {
+        return false;
+    }
+
+    let shared = reason.intersection(&criteria).count();
+    let jaccard = shared as f64 / reason.union(&criteria).count() as f64;
+    jaccard >= 0.8
+}
+
+/// Parsed judge decision plus the exchange that produced it
+struct Verdict {
+    passed: bool,
+    /// The judge's self-reported certainty, 0.0-1.0, when it gave one
+    confidence: Option<f64>,
+    reason: String,
+    /// One exchange per judge that voted
+    exchanges: Vec<JudgeExchange>,
+}
+
+impl Verdict {
+    /// Combine the verdicts of a judge panel; an even split is settled by `tie`
+    ///
+    /// When the judges disagree, every judge's vote and reason goes into the reason, along with
+    /// the tie policy if it applied. `escalated` marks a last verdict cast to break a tie; one
+    /// still tied without it fails.
+    fn majority(verdicts: Vec<Verdict>, tie: JudgeTie, escalated: bool) -> Verdict {
+        let votes = verdicts.iter().filter(|v| v.passed).count();
+        let tied = votes * 2 == verdicts.len();
+        let passed = if tied {
+            tie == JudgeTie::FailOpen
+        } else {
+            votes * 2 > verdicts.len()
+        };
+        let confidence = verdicts
+            .iter()
+            .filter(|v| v.passed == passed)
+            .filter_map(|v| v.confidence)
+            .reduce(f64::min);
+
+        let reason = if votes == 0 || votes == verdicts.len() {
+            verdicts[0].reason.clone()
+        } else {
+            let tie = match (tied, escalated) {
+                (true, _) if passed => format!(" (a tie counts as pass: {})", tie.name()),
+                (true, _) => format!(" (a tie counts as fail: {})", tie.name()),
+                (false, true) => " (a tie, escalated to a deciding vote)".to_string(),
+                (false, false) => String::new(),
+            };
+            let ballots = verdicts
+                .iter()
+                .map(|v| {
+                    let mark = if v.passed { "pass" } else { "fail" };
+                    let model = v.exchanges.first().map_or("judge", |e| e.model.as_str());
+                    format!("[{}] {}: {}", mark, model, v.reason)
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            format!(
+                "{}/{} judges passed{}: {}",
+                votes,
+                verdicts.len(),
+                tie,
+                ballots
+            )
+        };
+
+        Verdict {
+            passed,
+            confidence,
+            reason,
+            exchanges: verdicts.into_iter().flat_map(|v| v.exchanges).collect(),
+        }
+    }
+}
+
+/// The judges grading an eval; usually one, several with --judges or --judge-samples
+#[derive(Clone)]
+struct JudgePanel {
+    judges: Vec<Judge>,
+    /// Verdicts asked of each judge
+    samples: usize,
+    /// How an even split is decided
+    tie: JudgeTie,
+}
+
+impl JudgePanel {
+    fn new(judges: Vec<Judge>) -> Self {
+        Self {
+            judges,
+            samples: 1,
+            tie: JudgeTie::default(),
+        }
+    }
+
+    fn with_tie(mut self, tie: JudgeTie) -> Self {
+        self.tie = tie;
+        self
+    }
+
+    fn with_language(mut self, language: Option<String>) -> Self {
+        self.judges = self
+            .judges
+            .into_iter()
+            .map(|j| j.with_language(language.clone()))
+            .collect();
+        self
+    }
+
+    /// Sample each judge `samples` times; more than one sample raises the judge temperature
+    fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        let temperature = (self.samples > 1).then_some(JUDGE_SAMPLE_TEMPERATURE);
+        for judge in &mut self.judges {
+            judge.temperature = temperature;
+        }
+        self
+    }
+
+    /// Reply limit and temperature from --judge-max-tokens/--judge-temperature; an explicit
+    /// temperature also wins over the --judge-samples one
+    fn with_request_params(mut self, max_tokens: u32, temperature: Option<f32>) -> Self {
+        for judge in &mut self.judges {
+            judge.max_tokens = max_tokens;
+            if temperature.is_some() {
+                judge.temperature = temperature;
+            }
+        }
+        self
+    }
+
+    /// Language every judge on the panel grades in
+    fn language(&self) -> Option<&String> {
+        self.judges.first().and_then(|j| j.language.as_ref())
+    }
+
+    /// Judges for the startup banner, e.g. "using gpt-5-mini via openai"
+    fn describe(&self) -> String {
+        let judges = self
+            .judges
+            .iter()
+            .map(|j| format!("{} via {}", j.model, j.provider))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match (self.judges.len(), self.samples) {
+            (1, 1) => format!("using {}", judges),
+            (n, 1) => format!("majority of {}: {}", n, judges),
+            (n, samples) => format!(
+                "majority of {} samples at temperature {}: {}",
+                n * samples,
+                self.judges[0]
+                    .temperature
+                    .unwrap_or(JUDGE_SAMPLE_TEMPERATURE),
+                judges
+            ),
+        }
+    }
+
+    /// Ask every judge, every sample at once, and return the majority verdict
+    async fn evaluate(
+        &self,
+        task: &str,
+        agent_output: &str,
+        criteria: &str,
+        tool_trace: Option<&str>,
+    ) -> Result<Verdict> {
+        let mut verdicts = futures::future::try_join_all(
+            self.judges
+                .iter()
+                .flat_map(|j| std::iter::repeat(j).take(self.samples))
+                .map(|j| j.evaluate(task, agent_output, criteria, tool_trace)),
+        )
+        .await?;
+
+        let tied = verdicts.iter().filter(|v| v.passed).count() * 2 == verdicts.len();
+        let escalated = tied && self.tie == JudgeTie::Escalate;
+        if escalated {
+            let decider = self.judges[0]
+                .evaluate(task, agent_output, criteria, tool_trace)
+                .await?;
+            verdicts.push(decider);
+        }
+        Ok(Verdict::majority(verdicts, self.tie, escalated))
+    }
+
+    /// Score with every judge and sample at once and take the median
+    ///
+    /// With an even count the lower middle score is used, so a split leans towards failing.
+    async fn score(
+        &self,
+        task: &str,
+        agent_output: &str,
+        rubric: &str,
+        tool_trace: Option<&str>,
+    ) -> Result<RubricScore> {
+        let mut scores = futures::future::try_join_all(
+            self.judges
+                .iter()
+                .flat_map(|j| std::iter::repeat(j).take(self.samples))
+                .map(|j| j.score(task, agent_output, rubric, tool_trace)),
+        )
+        .await?;
+        scores.sort_by_key(|s| s.score);
+
+        let exchanges = scores.iter().flat_map(|s| s.exchanges.clone()).collect();
+        let all: Vec<String> = scores.iter().map(|s| s.score.to_string()).collect();
+        let median = scores.swap_remove((scores.len() - 1) / 2);
+        let reason = if all.len() > 1 {
+            format!("median of scores {}: {}", all.join(", "), median.reason)
+        } else {
+            median.reason
+        };
+        Ok(RubricScore {
+            score: median.score,
+            reason,
+            exchanges,
+        })
+    }
+}
+
+/// A judge call's full prompt and raw response, kept for debugging verdicts
+#[derive(Debug, Clone, Serialize)]
+struct JudgeExchange {
+    model: String,
+    prompt: String,
+    response: String,
+    latency_ms: u64,
+    attempts: u32,
+}
+
+impl EvalArtifacts {
+    /// Add to a metric, so repeated measurements within one eval accumulate
+    fn add_metric(&mut self, name: &str, value: f64) {
+        *self.metrics.entry(name.to_string()).or_insert(0.0) += value;
+    }
+
+    /// Keep the judge exchanges and fold their cost into the per-eval judge totals
+    ///
+    /// With several verdicts (sub-goals) the eval keeps the least confident one.
+    fn record_judge(&mut self, exchanges: Vec<JudgeExchange>, confidence: Option<f64>) {
+        if let Some(c) = confidence {
+            let lowest = self.judge_confidence.get_or_insert(c);
+            *lowest = lowest.min(c);
+        }
+        for exchange in exchanges {
+            *self.judge_latency_ms.get_or_insert(0) += exchange.latency_ms;
+            *self.judge_attempts.get_or_insert(0) += exchange.attempts;
+            self.judge_exchanges.push(exchange);
+        }
+    }
+}
+
+/// Run the judge panel and record its exchanges in the eval's artifacts
+async fn judge_output(
+    judge: &JudgePanel,
+    task: &str,
+    output: &str,
+    criteria: &str,
+    tool_trace: Option<&str>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    match judge.evaluate(task, output, criteria, tool_trace).await {
+        Ok(verdict) => {
+            if let Some(confidence) = verdict.confidence {
+                artifacts
+                    .metrics
+                    .insert("judge_confidence".to_string(), confidence);
+            }
+            artifacts.record_judge(verdict.exchanges, verdict.confidence);
+            artifacts.metrics.insert(
+                "judge_score".to_string(),
+                if verdict.passed { 1.0 } else { 0.0 },
+            );
+            if verdict.passed {
+                EvalResult::Pass
+            } else {
+                EvalResult::Fail(verdict.reason)
+            }
+        }
+        Err(e) if e.is::<UnparseableVerdict>() => EvalResult::Fail(e.to_string()),
+        Err(e) => EvalResult::Error(format!("Judge error: {}", e)),
+    }
+}
+
+/// Score the output against `then.rubric` and pass if the score reaches `min_score` (0-10)
+async fn judge_rubric(
+    judge: &JudgePanel,
+    task: &str,
+    output: &str,
+    rubric: &str,
+    min_score: f64,
+    tool_trace: Option<&str>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    match judge.score(task, output, rubric, tool_trace).await {
+        Ok(scored) => {
+            artifacts.record_judge(scored.exchanges, None);
+            let score = f64::from(scored.score);
+            // Reported on the same 0.0-1.0 scale as partial-credit scores
+            artifacts.score = Some(score / 10.0);
+            artifacts
+                .metrics
+                .insert("judge_score".to_string(), score / 10.0);
+            artifacts.metrics.insert("rubric_score".to_string(), score);
+            if score >= min_score {
+                EvalResult::Pass
+            } else {
+                EvalResult::Fail(format!(
+                    "Score {}/10 below {}: {}",
+                    scored.score, min_score, scored.reason
+                ))
+            }
+        }
+        Err(e) if e.is::<UnparseableVerdict>() => {
+            EvalResult::Fail("judge returned unparseable score".to_string())
+        }
+        Err(e) => EvalResult::Error(format!("Judge error: {}", e)),
+    }
+}
+
+/// Outcome of judging one weighted sub-goal of a partial-credit eval
+#[derive(Debug, Clone, Serialize)]
+struct SubgoalResult {
+    criteria: String,
+    weight: f64,
+    passed: bool,
+    reason: String,
+}
+
+/// Judge each `then.subgoals` entry independently and pass on the weighted score
+///
+/// Sub-goals are either plain criteria strings or `{"criteria": ..., "weight": ...}`.
+async fn judge_subgoals(
+    judge: &JudgePanel,
+    task: &str,
+    output: &str,
+    subgoals: &[serde_json::Value],
+    min_score: f64,
+    tool_trace: Option<&str>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let mut results = Vec::with_capacity(subgoals.len());
+    for goal in subgoals {
+        let (criteria, weight) = match goal {
+            serde_json::Value::String(c) => (c.as_str(), 1.0),
+            _ => match goal.get("criteria").and_then(|c| c.as_str()) {
+                Some(c) => (
+                    c,
+                    goal.get("weight").and_then(|w| w.as_f64()).unwrap_or(1.0),
+                ),
+                None => return EvalResult::Fail(format!("Sub-goal missing criteria: {}", goal)),
+            },
+        };
+
+        match judge.evaluate(task, output, criteria, tool_trace).await {
+            Ok(verdict) => {
+                artifacts.record_judge(verdict.exchanges, verdict.confidence);
+                results.push(SubgoalResult {
+                    criteria: criteria.to_string(),
+                    weight,
+                    passed: verdict.passed,
+                    reason: verdict.reason,
+                });
+            }
+            Err(e) if e.is::<UnparseableVerdict>() => return EvalResult::Fail(e.to_string()),
+            Err(e) => return EvalResult::Error(format!("Judge error: {}", e)),
+        }
+    }
+
+    let total: f64 = results.iter().map(|r| r.weight).sum();
+    let earned: f64 = results.iter().filter(|r| r.passed).map(|r| r.weight).sum();
+    let score = if total > 0.0 { earned / total } else { 0.0 };
+
+    let breakdown = results
+        .iter()
+        .map(|r| {
+            let mark = if r.passed { "pass" } else { "fail" };
+            format!("[{}] {} ({})", mark, r.criteria, r.reason)
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    let met = results.iter().filter(|r| r.passed).count();
+
+    artifacts.score = Some(score);
+    artifacts.metrics.insert("judge_score".to_string(), score);
+    artifacts.subgoals = results;
+
+    if score >= min_score {
+        EvalResult::Pass
+    } else {
+        EvalResult::Fail(format!(
+            "Score {:.2} below {:.2} ({}/{} sub-goals): {}",
+            score,
+            min_score,
+            met,
+            artifacts.subgoals.len(),
+            breakdown
+        ))
+    }
+}
+
+/// Provider serving `model`, from the model name's prefix
+fn provider_for_model(model: &str) -> &'static str {
+    if model.starts_with("claude") {
+        "anthropic"
+    } else if model.starts_with("gemini") {
+        "gemini"
+    } else {
+        "openai"
+    }
+}
+
+/// Build a judge for `model` on `provider`; parsing its verdict is the same for every provider
+fn create_judge(provider: &'static str, model: &str) -> Result<Judge> {
+    if let Some(reason) = provider_unavailable(provider) {
+        anyhow::bail!(reason);
+    }
+    Ok(Judge::new(
+        llm_client_for(provider)?,
+        provider,
+        model.to_string(),
+    ))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Eval {
+    id: String,
+    name: String,
+    description: String,
+    category: String,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    requires_key: Option<String>,
+    /// Fail the eval if it runs longer than this; overrides --timeout
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+    /// Millisecond variant of `timeout_secs` for fast evals; wins over it when both are set
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    /// Free-form labels for --tag/--exclude-tag, e.g. "smoke" or "slow"
+    #[serde(default)]
+    tags: Vec<String>,
+    given: serde_json::Value,
+    when: serde_json::Value,
+    then: serde_json::Value,
+    /// Name of the file the eval was loaded from
+    #[serde(skip)]
+    file: String,
+}
+
+#[derive(Debug)]
+enum EvalResult {
+    Pass,
+    Fail(String),
+    Skip(String),
+    /// The eval never reached a verdict because a provider, judge or server call broke
+    Error(String),
+}
+
+impl EvalResult {
+    /// Failures and errors both fail the run
+    fn is_failure(&self) -> bool {
+        matches!(self, EvalResult::Fail(_) | EvalResult::Error(_))
+    }
+
+    /// The status string used in machine-readable output
+    fn status(&self) -> &'static str {
+        match self {
+            EvalResult::Pass => "pass",
+            EvalResult::Fail(_) => "fail",
+            EvalResult::Skip(_) => "skip",
+            EvalResult::Error(_) => "error",
+        }
+    }
+
+    fn reason(&self) -> Option<&str> {
+        match self {
+            EvalResult::Pass => None,
+            EvalResult::Fail(r) | EvalResult::Skip(r) | EvalResult::Error(r) => Some(r),
+        }
+    }
+
+    /// This result as the serializable outcome of the eval it belongs to
+    fn outcome<'a>(&'a self, id: &'a str, name: &'a str, category: &'a str) -> EvalOutcome<'a> {
+        EvalOutcome {
+            id,
+            name,
+            category,
+            status: self.status(),
+            reason: self.reason(),
+        }
+    }
+}
+
+/// Diagnostic data collected while an eval runs, surfaced in verbose and JSON output
+#[derive(Debug, Default, Serialize)]
+struct EvalArtifacts {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    judge_exchanges: Vec<JudgeExchange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    thinking: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCallRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subgoals: Vec<SubgoalResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    judge_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    judge_attempts: Option<u32>,
+    /// Lowest confidence the judge reported for this eval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    judge_confidence: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    loop_errors: Vec<String>,
+    /// Numeric signals for dashboards (latency_ms, prompt_tokens, iterations, judge_score, ...)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metrics: BTreeMap<String, f64>,
+}
+
+impl EvalArtifacts {
+    fn is_empty(&self) -> bool {
+        self.judge_exchanges.is_empty()
+            && self.thinking.is_empty()
+            && self.tool_calls.is_empty()
+            && self.score.is_none()
+            && self.total_tokens.is_none()
+            && self.judge_attempts.is_none()
+            && self.judge_confidence.is_none()
+            && self.loop_errors.is_empty()
+            && self.metrics.is_empty()
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load .env file from current directory or parent directories
+    let _ = dotenvy::dotenv();
+
+    let args = Args::parse();
+
+    if let Some(Command::Completions { shell }) = &args.command {
+        let mut cmd = Args::command();
+        let name = cmd.get_name().to_string();
+        clap_complete::generate(*shell, &mut cmd, name, &mut std::io::stdout());
+        return Ok(());
+    }
+    if let Some(Command::MockMcpServer { tools }) = &args.command {
+        return mock_mcp::serve(tools);
+    }
+
+    if let Some(path) = &args.mock_llm {
+        let mock = mock_llm::MockLlmClient::from_file(path)?;
+        let _ = MOCK_LLM.set(Arc::new(mock));
+    }
+
+    let cassette = match (&args.record, &args.replay, &args.cassette) {
+        (Some(dir), _, _) => Some((dir, CassetteMode::Record)),
+        (_, Some(dir), _) => Some((dir, CassetteMode::Replay)),
+        (_, _, dir) => dir.as_ref().map(|dir| (dir, args.cassette_mode)),
+    };
+    if let Some((dir, mode)) = cassette {
+        if mode == CassetteMode::Record {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create cassette dir {}", dir.display()))?;
+        }
+        let _ = CASSETTE.set((dir.clone(), mode));
+    }
+    if let Some(dir) = &args.trace_dir {
+        trace::enable(dir)?;
+    }
+    let _ = AGENT_MODEL.set(args.agent_model.clone());
+
+    // Printed even for machine-readable formats (on stderr), so any run can be repeated
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let _ = RUN_SEED.set(seed);
+    if !args.list && !args.validate {
+        eprintln!(
+            "{}",
+            format!("Seed {} (repeat with --seed {})", seed, seed).dimmed()
+        );
+    }
+    // TODO(seed): drop this once send_request can pass the seed to every provider
+    if args.seed.is_some() && !args.list && !args.validate && !offline_llm() {
+        eprintln!(
+            "{} --seed reaches only --shuffle and Ollama requests; Anthropic, OpenAI and Gemini requests are sent unseeded",
+            "warning:".yellow().bold()
+        );
+    }
+
+    let profiling = args.profile || args.profile_folded.is_some();
+    if profiling {
+        enable_profile();
+    }
+    if args.watch {
+        return watch(&args, profiling).await;
+    }
+    if run_suite(&args, profiling).await? {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Seed from --seed, or generated at startup; watch mode keeps it across re-runs
+///
+/// It drives --shuffle and is sent with Ollama requests. Temperatures are fixed (0, or what the
+/// eval or judge flags set), so there is no temperature choice for it to make.
+static RUN_SEED: OnceLock<u64> = OnceLock::new();
+
+fn run_seed() -> u64 {
+    RUN_SEED.get().copied().unwrap_or_default()
+}
+
+/// RNG for one use of randomness, derived from the run seed and `stream`
+///
+/// Each use gets its own stream instead of sharing one RNG, so concurrent evals finishing in a
+/// different order can't change what another use draws. Per-eval randomness should pass the
+/// eval id.
+fn seeded_rng(stream: &str) -> StdRng {
+    // FNV-1a, like cassette hashes, so streams are stable across Rust versions
+    let hash = stream.bytes().fold(0xcbf29ce484222325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    StdRng::seed_from_u64(run_seed() ^ hash)
+}
+
+/// Load, run and report the selected evals once; true when the process should exit 1
+async fn run_suite(args: &Args, profiling: bool) -> Result<bool> {
+    let run_started = Instant::now();
+
+    let files = eval_files(&args.evals)?;
+    let mut evals = load_evals(
+        &files,
+        &args.category,
+        args.id.as_deref(),
+        &args.tag,
+        &args.exclude_tag,
+        args.lenient,
+    )?;
+
+    if let Some(since) = &args.since {
+        // Any file outside a git work tree means --since can't be trusted, so run everything
+        let mut changed = HashSet::new();
+        let mut tracked = true;
+        for file in &files {
+            match changed_eval_ids(file, since)? {
+                Some(ids) => changed.extend(ids),
+                None => {
+                    tracked = false;
+                    break;
+                }
+            }
+        }
+        if tracked {
+            evals.retain(|e| changed.contains(&e.id));
+        }
+    }
+    if let Some(shard) = args.shard {
+        evals = shard.select(evals);
+    }
+    // After sharding, which must split the loaded order the same way on every machine
+    if args.shuffle {
+        evals.shuffle(&mut seeded_rng("order"));
+    }
+    record_timing("load", run_started.elapsed());
+
+    let format = if args.json {
+        OutputFormat::Json
+    } else {
+        args.format
+    };
+    // Only human output carries banners; other formats must stay machine-readable
+    let human = format == OutputFormat::Human;
+
+    if args.validate {
+        let problems = validation_problems(&evals);
+        for (id, problem) in &problems {
+            println!("{} {}: {}", "INVALID".red().bold(), id, problem);
+        }
+        let invalid = problems
+            .iter()
+            .map(|(id, _)| id)
+            .collect::<HashSet<_>>()
+            .len();
+        if invalid > 0 {
+            println!("\n{} of {} evals invalid", invalid, evals.len());
+            return Ok(true);
+        }
+        println!("All {} evals valid", evals.len());
+        return Ok(false);
+    }
+
+    if args.list {
+        print_eval_list(&evals, format == OutputFormat::Json);
+        // Listing is the dry run before spending API budget, so malformed evals fail it
+        let problems = if args.no_validate {
+            Vec::new()
+        } else {
+            validation_problems(&evals)
+        };
+        for (id, problem) in &problems {
+            eprintln!("{} {}: {}", "error:".red().bold(), id, problem);
+        }
+        if !problems.is_empty() {
+            return Ok(true);
+        }
+        return Ok(false);
+    }
+
+    // Authoring mistakes fail here rather than as a confusing mid-run failure
+    if !args.no_validate {
+        let problems = validation_problems(&evals);
+        if !problems.is_empty() {
+            let report = problems
+                .iter()
+                .map(|(id, problem)| format!("{}: {}", id, problem))
+                .collect::<Vec<_>>();
+            if !args.lenient {
+                anyhow::bail!(
+                    "invalid evals (run with --validate to list them, or --no-validate to skip the check):\n  {}",
+                    report.join("\n  ")
+                );
+            }
+            for line in &report {
+                eprintln!("{} skipping {}", "warning:".yellow().bold(), line);
+            }
+            let invalid: HashSet<&str> = problems.iter().map(|(id, _)| id.as_str()).collect();
+            evals.retain(|e| !invalid.contains(e.id.as_str()));
+        }
+    }
+
+    // Read before any eval runs, so a bad path doesn't waste the run's API budget
+    let baseline = match &args.baseline {
+        Some(path) => Some((baseline::Baseline::load(path)?, defined_eval_ids(&files))),
+        None => None,
+    };
+
+    // Create judge if API key is available; with neither --judge-model nor --judge-provider,
+    // fall back through the providers until one has a key
+    let candidates: Vec<(&'static str, String)> = match (&args.judge_model, args.judge_provider) {
+        (Some(model), Some(provider)) => vec![(provider.name(), model.clone())],
+        (Some(model), None) => vec![(provider_for_model(model), model.clone())],
+        (None, Some(provider)) => vec![(provider.name(), provider.default_model().to_string())],
+        (None, None) => [
+            JudgeProvider::Openai,
+            JudgeProvider::Anthropic,
+            JudgeProvider::Gemini,
+        ]
+        .iter()
+        .map(|p| (p.name(), p.default_model().to_string()))
+        .collect(),
+    };
+    let mut judge = None;
+    let mut unavailable = Vec::new();
+    if args.judges.is_empty() {
+        for (provider, model) in candidates {
+            match create_judge(provider, &model) {
+                Ok(j) => {
+                    judge = Some(JudgePanel::new(vec![j]));
+                    break;
+                }
+                Err(e) => unavailable.push(format!("{}: {}", provider, e)),
+            }
+        }
+    } else {
+        // A vote is only meaningful with every requested judge, so one missing key disables them all
+        let mut panel = Vec::new();
+        for model in &args.judges {
+            match create_judge(provider_for_model(model), model) {
+                Ok(j) => panel.push(j),
+                Err(e) => unavailable.push(format!("{}: {}", model, e)),
+            }
+        }
+        if unavailable.is_empty() {
+            judge = Some(JudgePanel::new(panel));
+        }
+    }
+    let judge = judge.map(|j| {
+        j.with_language(args.judge_language.clone())
+            .with_samples(args.judge_samples)
+            .with_request_params(args.judge_max_tokens, args.judge_temperature)
+            .with_tie(args.judge_tie)
+    });
+    if human {
+        let banner = match &judge {
+            Some(j) => format!("Judge agent enabled ({})", j.describe()),
+            None => format!("Judge agent disabled ({})", unavailable.join("; ")),
+        };
+        eprintln!("{}", banner.dimmed());
+    }
+
+    if human {
+        eprintln!("\n{} {} evals\n", "Running".bold().cyan(), evals.len());
+    }
+
+    let opts = RunOptions {
+        verbose: args.verbose,
+        deterministic: args.deterministic,
+        capture_thinking: args.capture_thinking,
+        min_score: args.min_score,
+        strict_params: args.strict_params,
+        timeout_secs: args.timeout,
+        transcript_store: args.transcript_store,
+    };
+
+    let formatter = build_formatter(format, args.failures_only, args.verbose);
+    let failed = AtomicUsize::new(0);
+    let mut results = Vec::with_capacity(evals.len());
+
+    let keep_exchanges = args.capture_on_pass || args.verbose;
+    let max_failures = args.max_failures;
+    let retries = args.retries;
+
+    // Evals start as slots free up and finish in any order; `buffered` yields them in input order.
+    // Each handler builds its own tools and clients, so concurrent evals share no state.
+    let mut runs = futures::stream::iter(&evals)
+        .map(|eval| {
+            let (opts, judge, failed) = (&opts, judge.as_ref(), &failed);
+            async move {
+                let run = match max_failures {
+                    Some(max) if failed.load(Ordering::SeqCst) >= max => EvalRunResult {
+                        id: eval.id.clone(),
+                        name: eval.name.clone(),
+                        category: eval.category.clone(),
+                        file: eval.file.clone(),
+                        result: EvalResult::Skip(format!(
+                            "Not run: --max-failures {} reached",
+                            max
+                        )),
+                        duration: Duration::ZERO,
+                        artifacts: None,
+                        attempts: 1,
+                    },
+                    _ => {
+                        let mut run = execute_eval(eval, opts, judge, human, keep_exchanges).await;
+                        // Retries stay in this task, so results still print in input order;
+                        // skips and errors are never retried
+                        let mut spent = run.duration;
+                        while run.attempts <= retries && matches!(run.result, EvalResult::Fail(_)) {
+                            let attempts = run.attempts + 1;
+                            run = execute_eval(eval, opts, judge, human, keep_exchanges).await;
+                            spent += run.duration;
+                            run.attempts = attempts;
+                        }
+                        run.duration = spent;
+                        run
+                    }
+                };
+                if run.result.is_failure() {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                }
+                run
+            }
+        })
+        .buffered(args.jobs.max(1));
+
+    let mut stopped_early = false;
+    while let Some(run) = runs.next().await {
+        // Sorted output can only be printed once every eval has finished
+        if args.sort_by.is_none() {
+            if let Some(line) = formatter.on_result(&run) {
+                println!("{}", line);
+            }
+        }
+        let stop = args.fail_fast && matches!(run.result, EvalResult::Fail(_));
+        results.push(run);
+        if stop {
+            stopped_early = true;
+            break;
+        }
+    }
+    // Dropping the stream cancels evals that are still in flight
+    drop(runs);
+
+    if stopped_early && human {
+        eprintln!(
+            "\n{} after the first failure (--fail-fast); {} of {} evals not reported",
+            "Stopped".bold().yellow(),
+            evals.len() - results.len(),
+            evals.len()
+        );
+    }
+
+    if args.rerun_errors && !stopped_early {
+        // results still line up with evals here; sorting happens below
+        let errored: Vec<usize> = results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| matches!(r.result, EvalResult::Error(_)))
+            .map(|(i, _)| i)
+            .collect();
+        if human && !errored.is_empty() {
+            eprintln!(
+                "\n{} {} evals that errored\n",
+                "Retrying".bold().cyan(),
+                errored.len()
+            );
+        }
+        for i in errored {
+            let run = execute_eval(&evals[i], &opts, judge.as_ref(), human, keep_exchanges).await;
+            if args.sort_by.is_none() {
+                if let Some(line) = formatter.on_result(&run) {
+                    println!("{}", line);
+                }
+            }
+            results[i] = run;
+        }
+    }
+
+    if let Some(sort_by) = args.sort_by {
+        sort_results(&mut results, sort_by);
+        for run in &results {
+            if let Some(line) = formatter.on_result(run) {
+                println!("{}", line);
+            }
+        }
+    }
+
+    // Diffed before skips can be dropped from the report below, so they aren't counted as not run
+    let compared = baseline.as_ref().map(|(baseline, defined)| {
+        let diff = baseline.diff(&results, defined);
+        (diff.render(), diff.has_regressions())
+    });
+
+    // Counted before skips can be dropped from the report below
+    let skipped = results
+        .iter()
+        .filter(|r| matches!(r.result, EvalResult::Skip(_)))
+        .count();
+
+    // Whichever of --include-skipped/--no-include-skipped came last wins
+    let include_skipped = args.include_skipped || !args.no_include_skipped;
+    if !human && !include_skipped {
+        results.retain(|r| !matches!(r.result, EvalResult::Skip(_)));
+    }
+
+    let render_started = Instant::now();
+    println!("{}", formatter.render(&results));
+    if human {
+        let elapsed = format!("Finished in {:.1}s", run_started.elapsed().as_secs_f64());
+        eprintln!("{}", elapsed.dimmed());
+    }
+
+    if let Some(path) = &args.junit {
+        let xml = build_formatter(OutputFormat::Junit, false, false).render(&results);
+        std::fs::write(path, xml)
+            .with_context(|| format!("Failed to write JUnit report to {}", path.display()))?;
+    }
+    if let Some(path) = &args.summary_json {
+        let json = report::render_summary(&results, run_started.elapsed(), run_seed());
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write summary to {}", path.display()))?;
+    }
+    record_timing("report", render_started.elapsed());
+
+    if profiling {
+        print_profile(run_started.elapsed());
+        if let Some(path) = &args.profile_folded {
+            write_profile_folded(path)?;
+        }
+    }
+
+    // With a baseline, failures it already had are tolerated and only regressions fail the run
+    match compared {
+        Some((comparison, regressed)) => {
+            eprintln!("{}", comparison);
+            if regressed {
+                return Ok(true);
+            }
+        }
+        None if results.iter().any(|r| r.result.is_failure()) => return Ok(true),
+        None => {}
+    }
+    // A skip is often a missing key or server, so CI can cap them instead of passing quietly
+    if let Some(max) = args.max_skips.filter(|max| skipped > *max) {
+        eprintln!(
+            "{} {} skips exceeds max-skips={}",
+            "FAILED:".red().bold(),
+            skipped,
+            max
+        );
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Quiet period after a change before re-running, so an editor's burst of writes runs once
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// --watch: run the selected evals, then again after every change to an eval file, until Ctrl-C
+///
+/// Load errors and failures only end the current run, so a half-edited file doesn't stop the watch.
+async fn watch(args: &Args, profiling: bool) -> Result<()> {
+    let (tx, mut changes) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        let edited = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        );
+        if edited
+            && event
+                .paths
+                .iter()
+                .any(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        {
+            let _ = tx.send(());
+        }
+    })?;
+    for (path, mode) in watch_paths(&args.evals) {
+        watcher
+            .watch(&path, mode)
+            .with_context(|| format!("Failed to watch {}", path.display()))?;
+    }
+
+    loop {
+        // Clear the screen and move the cursor home
+        eprint!("\x1B[2J\x1B[H");
+        tokio::select! {
+            outcome = run_suite(args, profiling) => {
+                if let Err(e) = outcome {
+                    eprintln!("{} {:#}", "error:".red().bold(), e);
+                }
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        eprintln!(
+            "\n{}",
+            "Watching eval files for changes (Ctrl-C to stop)".dimmed()
+        );
+
+        tokio::select! {
+            change = changes.recv() => {
+                if change.is_none() {
+                    return Ok(());
+                }
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        while let Ok(Some(())) = tokio::time::timeout(WATCH_DEBOUNCE, changes.recv()).await {}
+    }
+}
+
+/// What --watch observes for each --evals value: directories recursively, otherwise the file's
+/// directory or a glob's fixed prefix, since editors often replace a file instead of writing it
+fn watch_paths(values: &[PathBuf]) -> Vec<(PathBuf, RecursiveMode)> {
+    let is_glob = |s: &str| s.contains(['*', '?', '[']);
+    let dir_or_cwd = |dir: PathBuf| {
+        if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        }
+    };
+    values
+        .iter()
+        .map(|value| {
+            if is_glob(&value.to_string_lossy()) {
+                let prefix: PathBuf = value
+                    .components()
+                    .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+                    .collect();
+                (dir_or_cwd(prefix), RecursiveMode::Recursive)
+            } else if value.is_dir() {
+                (value.clone(), RecursiveMode::Recursive)
+            } else {
+                let parent = value.parent().map(Path::to_path_buf).unwrap_or_default();
+                (dir_or_cwd(parent), RecursiveMode::NonRecursive)
+            }
+        })
+        .collect()
+}
+
+/// Print `evals` for --list, as aligned columns or a JSON array
+fn print_eval_list(evals: &[Eval], json: bool) {
+    if json {
+        let list: Vec<serde_json::Value> = evals
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "id": e.id,
+                    "category": e.category,
+                    "name": e.name,
+                    "file": e.file,
+                    "requires_key": e.requires_key,
+                    "tags": e.tags,
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&list).expect("eval list serializes")
+        );
+        return;
+    }
+
+    let id_width = evals.iter().map(|e| e.id.len()).max().unwrap_or(0);
+    let category_width = evals.iter().map(|e| e.category.len()).max().unwrap_or(0);
+    let name_width = evals.iter().map(|e| e.name.len()).max().unwrap_or(0);
+    for e in evals {
+        let line = format!(
+            "{:<id_width$}  {:<category_width$}  {:<name_width$}  {}",
+            e.id,
+            e.category,
+            e.name,
+            e.requires_key.as_deref().unwrap_or("")
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// The eval a provider call is made for, so profiles and cassettes can attribute it
+struct EvalScope {
+    id: String,
+    category: String,
+    /// `when.temperature`, applied to the eval's requests that don't set their own
+    temperature: Option<f32>,
+}
+
+tokio::task_local! {
+    static CURRENT_EVAL: EvalScope;
+}
+
+/// Id of the eval being polled, or "unknown" outside of one
+fn current_eval_id() -> String {
+    CURRENT_EVAL
+        .try_with(|e| e.id.clone())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// An eval id made safe to use as a file name in cassette and trace directories
+fn eval_file_name(eval_id: &str) -> String {
+    eval_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Run one eval and package its result, timing and artifacts for the formatters
+async fn execute_eval(
+    eval: &Eval,
+    opts: &RunOptions,
+    judge: Option<&JudgePanel>,
+    heartbeat: bool,
+    keep_exchanges: bool,
+) -> EvalRunResult {
+    let mut artifacts = EvalArtifacts::default();
+    let started = Instant::now();
+    let scope = EvalScope {
+        id: eval.id.clone(),
+        category: eval.category.clone(),
+        temperature: eval
+            .when
+            .get("temperature")
+            .and_then(|t| t.as_f64())
+            .map(|t| t as f32),
+    };
+    let mut result = CURRENT_EVAL
+        .scope(
+            scope,
+            with_heartbeat(
+                &eval.id,
+                heartbeat,
+                run_eval(eval, opts, judge, &mut artifacts),
+            ),
+        )
+        .await;
+
+    let duration = started.elapsed();
+
+    // Replaying an unrecorded request must not quietly pass or fail on an empty response
+    let misses = cassette::take_misses(&eval.id);
+    trace::flush(&eval.id);
+    if !misses.is_empty() {
+        result = EvalResult::Error(misses.join("; "));
+    }
+    record_timing(&format!("eval;{}", eval.category), duration);
+    artifacts.add_metric("latency_ms", duration.as_millis() as f64);
+    if !artifacts.tool_calls.is_empty() {
+        artifacts.add_metric("tool_calls", artifacts.tool_calls.len() as f64);
+    }
+
+    // Passing evals only keep judge exchanges when explicitly requested
+    if matches!(result, EvalResult::Pass) && !keep_exchanges {
+        artifacts.judge_exchanges.clear();
+    }
+
+    EvalRunResult {
+        id: eval.id.clone(),
+        name: eval.name.clone(),
+        category: eval.category.clone(),
+        file: eval.file.clone(),
+        result,
+        duration,
+        artifacts: (!artifacts.is_empty()).then_some(artifacts),
+        attempts: 1,
+    }
+}
+
+/// How long an eval runs silently before the first heartbeat, and the gap between heartbeats
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Drive an eval to completion, printing a dimmed "still running" line while it is slow
+async fn with_heartbeat<F>(id: &str, enabled: bool, eval: F) -> EvalResult
+where
+    F: std::future::Future<Output = EvalResult>,
+{
+    if !enabled {
+        return eval.await;
+    }
+
+    let started = Instant::now();
+    let mut ticks = tokio::time::interval_at(
+        tokio::time::Instant::now() + HEARTBEAT_INTERVAL,
+        HEARTBEAT_INTERVAL,
+    );
+    tokio::pin!(eval);
+    loop {
+        tokio::select! {
+            result = &mut eval => return result,
+            _ = ticks.tick() => {
+                let line = format!("still running {} ({}s)…", id, started.elapsed().as_secs());
+                eprintln!("{}", line.dimmed());
+            }
+        }
+    }
+}
+
+/// Expand `--evals` entries into the files to load
+///
+/// Globs are matched, directories are searched recursively for .jsonl files, and a file
+/// reached through more than one entry is only loaded once.
+fn eval_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+
+    for path in paths {
+        let pattern = path.to_string_lossy();
+        let matched = if pattern.contains(['*', '?', '[']) {
+            let matched = glob::glob(&pattern)
+                .with_context(|| format!("Invalid glob {}", pattern))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if matched.is_empty() {
+                anyhow::bail!("No eval files match {}", pattern);
+            }
+            matched
+        } else {
+            vec![path.clone()]
+        };
+
+        for entry in matched {
+            let mut found = Vec::new();
+            if entry.is_dir() {
+                collect_jsonl(&entry, &mut found)?;
+            } else {
+                found.push(entry);
+            }
+            for file in found {
+                let key = file.canonicalize().unwrap_or_else(|_| file.clone());
+                if seen.insert(key) {
+                    files.push(file);
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Every .jsonl file under `dir`, sorted so runs load evals in a stable order
+fn collect_jsonl(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            collect_jsonl(&entry, files)?;
+        } else if entry.extension().is_some_and(|e| e == "jsonl") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn load_evals(
+    files: &[PathBuf],
+    category_filter: &[String],
+    id_filter: Option<&str>,
+    tag_filter: &[String],
+    excluded_tags: &[String],
+    lenient: bool,
+) -> Result<Vec<Eval>> {
+    let mut evals = Vec::new();
+    // Where each id was first defined, so a second definition can point at both
+    let mut defined: HashMap<String, String> = HashMap::new();
+
+    for file_path in files {
+        let bytes = std::fs::read(file_path)
+            .with_context(|| format!("Failed to open {}", file_path.display()))?;
+
+        for (location, parsed) in parse_eval_file(&bytes, file_path, lenient)? {
+            let mut eval: Eval = match parsed {
+                Ok(eval) => eval,
+                Err(e) if lenient => {
+                    eprintln!(
+                        "{} skipping {}: {}",
+                        "warning:".yellow().bold(),
+                        location,
+                        e
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to parse {}", location)),
+            };
+            eval.file = file_path
+                .file_name()
+                .unwrap_or(file_path.as_os_str())
+                .to_string_lossy()
+                .into_owned();
+
+            // A mistyped category would otherwise only surface as a skip at run time
+            if !CATEGORIES.contains(&eval.category.as_str()) {
+                let message = format!(
+                    "unknown category \"{}\" for {} on {} (expected one of: {})",
+                    eval.category,
+                    eval.id,
+                    location,
+                    CATEGORIES.join(", ")
+                );
+                if !lenient {
+                    anyhow::bail!(message);
+                }
+                eprintln!("{} {}", "warning:".yellow().bold(), message);
+            }
+
+            if let Some(first) = defined.get(&eval.id) {
+                let message = format!(
+                    "duplicate eval id \"{}\" on {} (first defined on {})",
+                    eval.id, location, first
+                );
+                if !lenient {
+                    anyhow::bail!(message);
+                }
+                eprintln!("{} {}, skipping it", "warning:".yellow().bold(), message);
+                continue;
+            }
+            defined.insert(eval.id.clone(), location);
+
+            // Apply filters; an eval matching any --category is kept
+            if !category_filter.is_empty() && !category_filter.contains(&eval.category) {
+                continue;
+            }
+            if let Some(id) = id_filter {
+                if eval.id != id {
+                    continue;
+                }
+            }
+            if !tag_filter.iter().all(|t| eval.tags.contains(t)) {
+                continue;
+            }
+            // An excluded tag drops the eval even when it also carries every --tag
+            if excluded_tags.iter().any(|t| eval.tags.contains(t)) {
+                continue;
+            }
+
+            evals.push(eval);
+        }
+    }
+
+    Ok(evals)
+}
+
+/// Split one evals file into records, each tagged with where it came from
+///
+/// A file holding a single JSON object or a JSON array of objects is read whole, so
+/// hand-written evals can be pretty-printed; anything else is treated as JSONL.
+fn parse_eval_file(
+    bytes: &[u8],
+    file_path: &Path,
+    lenient: bool,
+) -> Result<Vec<(String, serde_json::Result<Eval>)>> {
+    match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(serde_json::Value::Array(items)) => {
+            return Ok(items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let location = format!("eval {} in {}", i + 1, file_path.display());
+                    (location, serde_json::from_value(item))
+                })
+                .collect());
+        }
+        Ok(item @ serde_json::Value::Object(_)) => {
+            return Ok(vec![(
+                file_path.display().to_string(),
+                serde_json::from_value(item),
+            )]);
+        }
+        _ => {}
+    }
+
+    let mut records = Vec::new();
+    // Split on raw bytes so one bad line can be pinpointed instead of failing the file
+    for (line_num, raw) in bytes.split(|b| *b == b'\n').enumerate() {
+        let location = format!("line {} in {}", line_num + 1, file_path.display());
+
+        let line = match std::str::from_utf8(raw) {
+            Ok(line) => std::borrow::Cow::Borrowed(line),
+            Err(e) if lenient => {
+                eprintln!(
+                    "{} invalid UTF-8 on {} ({}), decoding lossily",
+                    "warning:".yellow().bold(),
+                    location,
+                    e
+                );
+                String::from_utf8_lossy(raw)
+            }
+            Err(e) => anyhow::bail!("Invalid UTF-8 on {}: {}", location, e),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let parsed = serde_json::from_str(&line);
+        records.push((location, parsed));
+    }
+    Ok(records)
+}
+
+/// Every eval id defined in `files`, filters aside, so --baseline can tell removed evals from
+/// ones that just weren't selected
+fn defined_eval_ids(files: &[PathBuf]) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    for file_path in files {
+        let records = std::fs::read(file_path)
+            .ok()
+            .and_then(|bytes| parse_eval_file(&bytes, file_path, false).ok())
+            .unwrap_or_default();
+        ids.extend(
+            records
+                .into_iter()
+                .filter_map(|(_, eval)| eval.ok().map(|e| e.id)),
+        );
+    }
+    ids
+}
+
+/// Ids of evals whose lines were added or modified since `since`
+///
+/// Returns None when the evals path isn't inside a git work tree, meaning run everything.
+fn changed_eval_ids(path: &Path, since: &str) -> Result<Option<HashSet<String>>> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+    let dir = if path.is_dir() {
+        path.clone()
+    } else {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+    };
+
+    let in_repo = std::process::Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(&dir)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !in_repo {
+        eprintln!(
+            "{} {} is not in a git repository, ignoring --since",
+            "warning:".yellow().bold(),
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    let output = std::process::Command::new("git")
+        .args(["diff", "--unified=0", "--no-color", since, "--"])
+        .arg(&path)
+        .current_dir(&dir)
+        .output()
+        .context("Failed to run git diff")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git diff against {} failed: {}",
+            since,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // Added and modified eval lines both show up as `+` lines carrying the full record
+    let diff = String::from_utf8_lossy(&output.stdout);
+    let ids = diff
+        .lines()
+        .filter(|l| l.starts_with('+') && !l.starts_with("+++"))
+        .filter_map(|l| serde_json::from_str::<serde_json::Value>(&l[1..]).ok())
+        .filter_map(|v| v.get("id").and_then(|id| id.as_str()).map(String::from))
+        .collect();
+
+    Ok(Some(ids))
+}
+
+async fn run_eval(
+    eval: &Eval,
+    opts: &RunOptions,
+    judge: Option<&JudgePanel>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    // Check for required API keys; mocked providers need none
+    if let Some(key) = &eval.requires_key {
+        if std::env::var(key).is_err() && !offline_llm() {
+            return EvalResult::Skip(format!("{} not set", key));
+        }
+    }
+
+    // Diagnostics go to stderr so they never end up inside a json/junit/tap report on stdout
+    if opts.verbose {
+        eprintln!("  given: {:?}", eval.given);
+        eprintln!("  when: {:?}", eval.when);
+        eprintln!("  then: {:?}", eval.then);
+    }
+
+    // Dispatch based on category
+    let dispatch = async {
+        match eval.category.as_str() {
+            "tools" => run_tool_eval(eval).await,
+            "hooks" => run_hook_eval(eval).await,
+            "agent" => run_agent_eval(eval, opts, judge, artifacts).await,
+            "subagent" => run_subagent_eval(eval, opts, judge, artifacts).await,
+            "transcript" => run_transcript_eval(eval, opts).await,
+            "mcp" => run_mcp_eval(eval).await,
+            "llm" => run_llm_eval(eval, opts, judge, artifacts).await,
+            _ => EvalResult::Skip(format!("Unknown category: {}", eval.category)),
+        }
+    };
+
+    // A stalled provider or server must not hang the whole run
+    let limit = match (eval.timeout_ms, eval.timeout_secs.or(opts.timeout_secs)) {
+        (Some(ms), _) => Some((Duration::from_millis(ms), format!("{}ms", ms))),
+        (None, Some(secs)) => Some((Duration::from_secs(secs), format!("{}s", secs))),
+        (None, None) => None,
+    };
+    match limit {
+        Some((limit, label)) => within(limit, &label, dispatch).await,
+        None => dispatch.await,
+    }
+}
+
+/// `eval`'s result, or a failure naming `label` once `limit` passes without one
+async fn within(
+    limit: Duration,
+    label: &str,
+    eval: impl std::future::Future<Output = EvalResult>,
+) -> EvalResult {
+    tokio::time::timeout(limit, eval)
+        .await
+        .unwrap_or_else(|_| EvalResult::Fail(format!("timed out after {}", label)))
+}
+
+/// Categories `run_eval` dispatches on
+const CATEGORIES: &[&str] = &[
+    "tools",
+    "hooks",
+    "agent",
+    "subagent",
+    "transcript",
+    "mcp",
+    "llm",
+];
+
+// ============================================================================
+// Eval Validation - Structural checks on given/when/then before anything runs
+// ============================================================================
+
+/// Providers an llm eval can name
+const PROVIDERS: &[&str] = &["anthropic", "openai", "gemini", "ollama"];
+
+/// JSON shapes a field can be required to have
+#[derive(Clone, Copy)]
+enum Shape {
+    String,
+    Number,
+    Bool,
+    Array,
+    Object,
+}
+
+impl Shape {
+    fn matches(self, value: &serde_json::Value) -> bool {
+        match self {
+            Shape::String => value.is_string(),
+            Shape::Number => value.is_number(),
+            Shape::Bool => value.is_boolean(),
+            Shape::Array => value.is_array(),
+            Shape::Object => value.is_object(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Shape::String => "a string",
+            Shape::Number => "a number",
+            Shape::Bool => "a boolean",
+            Shape::Array => "an array",
+            Shape::Object => "an object",
+        }
+    }
+}
+
+/// Collects the problems found in one eval
+struct Validator<'a> {
+    eval: &'a Eval,
+    problems: Vec<String>,
+}
+
+impl<'a> Validator<'a> {
+    /// Returned with the eval's lifetime, so problems can be pushed while a field is borrowed
+    fn section(&self, section: &str) -> &'a serde_json::Value {
+        match section {
+            "given" => &self.eval.given,
+            "when" => &self.eval.when,
+            _ => &self.eval.then,
+        }
+    }
+
+    /// `section.key` must have `shape` when present
+    fn optional(&mut self, section: &str, key: &str, shape: Shape) {
+        if let Some(value) = self.section(section).get(key) {
+            if !shape.matches(value) {
+                self.problems
+                    .push(format!("{}.{} must be {}", section, key, shape.name()));
+            }
+        }
+    }
+
+    /// `section.key` must be present and have `shape`
+    fn required(&mut self, section: &str, key: &str, shape: Shape) {
+        if self.section(section).get(key).is_none() {
+            self.problems
+                .push(format!("{}.{} is required", section, key));
+        } else {
+            self.optional(section, key, shape);
+        }
+    }
+}
+
+/// Every structural problem in an eval, or none if its shape fits its category
+///
+/// Only checks what the handlers rely on; unknown keys are left alone so evals can carry
+/// fields other runners read.
+fn validate_eval(eval: &Eval) -> Vec<String> {
+    let mut v = Validator {
+        eval,
+        problems: Vec::new(),
+    };
+    for section in ["given", "when", "then"] {
+        if !v.section(section).is_object() {
+            v.problems.push(format!("{} must be an object", section));
+        }
+    }
+    if !v.problems.is_empty() {
+        return v.problems;
+    }
+
+    v.optional("when", "action", Shape::String);
+    v.optional("given", "tools", Shape::Array);
+    let named = eval.given.get("tools").and_then(|t| t.as_array());
+    for name in named.into_iter().flatten().filter_map(|t| t.as_str()) {
+        if !tools::is_builtin(name) {
+            v.problems.push(format!(
+                "given.tools names unknown builtin tool '{}' (known: {})",
+                name,
+                tools::BUILTIN_TOOLS.join(", ")
+            ));
+        }
+    }
+    v.optional("when", "input", Shape::Object);
+    v.optional("when", "params", Shape::Object);
+    v.optional("when", "temperature", Shape::Number);
+    v.optional("then", "success", Shape::Bool);
+    v.optional("then", "expect", Shape::String);
+    v.optional("then", "equals", Shape::String);
+    v.optional("then", "max_tokens", Shape::Number);
+    v.optional("then", "max_cost_usd", Shape::Number);
+    if let Some(pattern) = eval.then.get("matches") {
+        match pattern.as_str().map(regex::Regex::new) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => v
+                .problems
+                .push(format!("then.matches is not a valid regex: {}", e)),
+            None => v.problems.push("then.matches must be a string".to_string()),
+        }
+    }
+
+    match eval.category.as_str() {
+        "tools" => {
+            v.required("when", "tool", Shape::String);
+            v.optional("given", "http_routes", Shape::Object);
+            v.optional("then", "error_contains", Shape::String);
+        }
+        "hooks" => {
+            v.required("given", "hooks", Shape::Array);
+            v.optional("when", "events", Shape::Array);
+            v.optional("then", "fired", Shape::Array);
+            v.optional("then", "action", Shape::String);
+            v.optional("then", "message_contains", Shape::String);
+            v.optional("then", "error_contains", Shape::String);
+        }
+        "agent" => {
+            v.optional("given", "model", Shape::String);
+            v.optional("when", "task", Shape::String);
+            v.optional("when", "prompt", Shape::String);
+            if eval.when.get("task").is_none() && eval.when.get("prompt").is_none() {
+                v.problems
+                    .push("when.task (or when.prompt) is required".to_string());
+            }
+            v.optional("then", "subgoals", Shape::Array);
+            v.optional("then", "rubric", Shape::String);
+            v.optional("then", "min_score", Shape::Number);
+        }
+        "subagent" => {
+            v.required("when", "action", Shape::String);
+            v.required("when", "prompt", Shape::String);
+            v.optional("given", "model", Shape::String);
+        }
+        "transcript" => {
+            v.required("when", "action", Shape::String);
+        }
+        "mcp" => {
+            v.required("when", "action", Shape::String);
+            v.optional("given", "mcp_server", Shape::String);
+            v.optional("given", "server_tools", Shape::Array);
+            v.optional("given", "command", Shape::String);
+            v.optional("given", "args", Shape::Array);
+            v.optional("given", "env", Shape::Object);
+            v.optional("then", "tools_include", Shape::Array);
+        }
+        "llm" => {
+            match eval.provider.as_deref() {
+                Some(provider) if !PROVIDERS.contains(&provider) => v.problems.push(format!(
+                    "unknown provider \"{}\" (expected one of: {})",
+                    provider,
+                    PROVIDERS.join(", ")
+                )),
+                _ => {}
+            }
+            v.optional("given", "model", Shape::String);
+            v.optional("when", "prompt", Shape::String);
+            v.optional("when", "content_blocks", Shape::Array);
+        }
+        _ => {}
+    }
+    v.problems
+}
+
+/// (eval id, problem) for every problem in `evals`, in eval order
+fn validation_problems(evals: &[Eval]) -> Vec<(String, String)> {
+    evals
+        .iter()
+        .flat_map(|e| {
+            validate_eval(e)
+                .into_iter()
+                .map(move |problem| (e.id.clone(), problem))
+        })
+        .collect()
+}
+
+// ============================================================================
+// Declared Tools - Builtins named in `given.tools`, plus tools the eval defines itself
+// ============================================================================
+
+/// A tool defined entirely by an eval's `given.tools` entry
+///
+/// `schema` is either a full JSON schema or the `{"field": "type"}` shorthand used by the
+/// tool evals. Calls return `returns` (stringified if it isn't a string), or fail with `error`.
+struct DeclaredTool {
+    name: String,
+    description: String,
+    schema: serde_json::Value,
+    returns: String,
+    error: Option<String>,
+}
+
+impl DeclaredTool {
+    fn from_spec(spec: &serde_json::Value) -> Result<Self> {
+        let name = spec
+            .get("name")
+            .and_then(|n| n.as_str())
+            .context("tool is missing \"name\"")?;
+        let description = spec
+            .get("description")
+            .and_then(|d| d.as_str())
+            .unwrap_or("");
+
+        let schema = match spec.get("schema") {
+            Some(schema) if schema.get("type").is_some() => schema.clone(),
+            Some(serde_json::Value::Object(fields)) => {
+                let properties: serde_json::Map<String, serde_json::Value> = fields
+                    .iter()
+                    .map(|(field, ty)| (field.clone(), serde_json::json!({ "type": ty })))
+                    .collect();
+                serde_json::json!({"type": "object", "properties": properties})
+            }
+            Some(other) => anyhow::bail!("schema for '{}' must be an object: {}", name, other),
+            None => serde_json::json!({"type": "object", "properties": {}}),
+        };
+
+        let returns = match spec.get("returns") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => format!("{} completed", name),
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            schema,
+            returns,
+            error: spec
+                .get("error")
+                .and_then(|e| e.as_str())
+                .map(str::to_string),
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for DeclaredTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn description(&self) -> &str {
+        &self.description
+    }
+    fn schema(&self) -> serde_json::Value {
+        self.schema.clone()
+    }
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        match &self.error {
+            Some(message) => Err(anyhow::anyhow!("{}", message)),
+            None => Ok(ToolResult::text(self.returns.clone())),
+        }
+    }
+}
+
+/// Registry over the `defaults` builtins plus an eval's tool entries, with every name registered
+///
+/// Entries are builtin names (`"add"`) or declared-tool specs. A spec named like a builtin gets
+/// the builtin, since the runner already provides it.
+async fn tool_registry(
+    entries: &[serde_json::Value],
+    defaults: &[&str],
+) -> Result<(Registry, Vec<String>)> {
+    let mut names: Vec<String> = defaults.iter().map(|n| n.to_string()).collect();
+    let mut declared = Vec::new();
+    for entry in entries {
+        let name = match entry.as_str() {
+            Some(name) => name,
+            None => match entry.get("name").and_then(|n| n.as_str()) {
+                Some(name) if tools::is_builtin(name) => name,
+                _ => {
+                    declared.push(DeclaredTool::from_spec(entry)?);
+                    continue;
+                }
+            },
+        };
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    let builtins: Vec<&str> = names.iter().map(String::as_str).collect();
+    let registry = tools::build_registry(&builtins).await?;
+    for tool in declared {
+        names.push(tool.name.clone());
+        registry.register(tool).await;
+    }
+    Ok((registry, names))
+}
+
+/// The eval's `given.tools` entries; absent means none
+fn given_tools(eval: &Eval) -> &[serde_json::Value] {
+    eval.given
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .map_or(&[], Vec::as_slice)
+}
+
+// ============================================================================
+// Tool Evals
+// ============================================================================
+
+/// Builtins every tool eval can call without listing them in `given.tools`
+const TOOL_EVAL_BUILTINS: &[&str] = &["add", "divide", "greet", "get_info"];
+
+async fn run_tool_eval(eval: &Eval) -> EvalResult {
+    // Tools the built-ins don't cover are declared by the eval itself
+    let registry = match tool_registry(given_tools(eval), TOOL_EVAL_BUILTINS).await {
+        Ok((registry, _)) => registry,
+        Err(e) => return EvalResult::Fail(format!("Invalid tool in given.tools: {}", e)),
+    };
+
+    if let Some(name) = eval.when.get("tool").and_then(|t| t.as_str()) {
+        let input = eval
+            .when
+            .get("input")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        // Networked tools talk to a local server instead of the internet; it lives for this call
+        let fixture = match eval.given.get("http_routes") {
+            Some(routes) => match http_fixture::serve(routes).await {
+                Ok(fixture) => Some(fixture),
+                Err(e) => return EvalResult::Fail(format!("HTTP fixture: {:#}", e)),
+            },
+            None => None,
+        };
+        let input = match &fixture {
+            Some(fixture) => http_fixture::substitute(&input, fixture.url()),
+            None => input,
+        };
+        return check_tool_execution(eval, &registry, name, input).await;
+    }
+
+    // Evals without `when.tool` fall back to their hand-written checks
+    match eval.id.as_str() {
+        "tool-001" => {
+            // tool_execution_basic - Add two numbers
             let tool = registry.get("add").await;
             if tool.is_none() {
                 return EvalResult::Fail("Tool 'add' not found".to_string());
@@ -547,240 +3033,982 @@ async fn run_tool_eval(eval: &Eval) -> EvalResult {
                 Err(e) => EvalResult::Fail(format!("Execution failed: {}", e)),
             }
         }
-        "tool-002" => {
-            // tool_not_found - Unknown tool returns None
-            let tool = registry.get("nonexistent").await;
-            if tool.is_none() {
-                EvalResult::Pass
-            } else {
-                EvalResult::Fail("Expected None for nonexistent tool".to_string())
+        "tool-002" => {
+            // tool_not_found - Unknown tool returns None
+            let tool = registry.get("nonexistent").await;
+            if tool.is_none() {
+                EvalResult::Pass
+            } else {
+                EvalResult::Fail("Expected None for nonexistent tool".to_string())
+            }
+        }
+        "tool-003" => {
+            // tool_invalid_input - Division by zero
+            let tool = registry.get("divide").await.unwrap();
+            let result = tool.execute(serde_json::json!({"a": 10, "b": 0})).await;
+            match result {
+                Err(_) => EvalResult::Pass,
+                Ok(_) => EvalResult::Fail("Expected error for division by zero".to_string()),
+            }
+        }
+        "tool-004" => {
+            // tool_result_string - Greet returns string with name
+            let tool = registry.get("greet").await.unwrap();
+            let result = tool.execute(serde_json::json!({"name": "World"})).await;
+            match result {
+                Ok(r) => {
+                    if r.content.contains("World") {
+                        EvalResult::Pass
+                    } else {
+                        EvalResult::Fail(format!("Expected 'World' in result, got: {}", r.content))
+                    }
+                }
+                Err(e) => EvalResult::Fail(format!("Execution failed: {}", e)),
+            }
+        }
+        "tool-005" => {
+            // tool_result_json - GetInfo returns valid JSON
+            let tool = registry.get("get_info").await.unwrap();
+            let result = tool.execute(serde_json::json!({})).await;
+            match result {
+                Ok(r) => {
+                    if serde_json::from_str::<serde_json::Value>(&r.content).is_ok() {
+                        EvalResult::Pass
+                    } else {
+                        EvalResult::Fail(format!("Result is not valid JSON: {}", r.content))
+                    }
+                }
+                Err(e) => EvalResult::Fail(format!("Execution failed: {}", e)),
+            }
+        }
+        _ => EvalResult::Skip(format!("Unknown tool eval: {}", eval.id)),
+    }
+}
+
+/// Execute `when.tool` with `when.input` and grade it against `then`
+///
+/// `then.success: false` expects an error, optionally narrowed by `error_type`
+/// (`tool_not_found` or `execution_error`) and by `error_contains` for execution errors.
+/// Successful calls are checked against `result_contains` and `result_is_json` when present.
+async fn check_tool_execution(
+    eval: &Eval,
+    registry: &Registry,
+    name: &str,
+    input: serde_json::Value,
+) -> EvalResult {
+    let expect_success = eval
+        .then
+        .get("success")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(true);
+    let error_type = eval.then.get("error_type").and_then(|e| e.as_str());
+
+    let tool = match registry.get(name).await {
+        Some(tool) => tool,
+        None if !expect_success && matches!(error_type, None | Some("tool_not_found")) => {
+            return EvalResult::Pass
+        }
+        None => return EvalResult::Fail(format!("Tool '{}' not found", name)),
+    };
+    if error_type == Some("tool_not_found") {
+        return EvalResult::Fail(format!("Expected tool '{}' to be missing", name));
+    }
+
+    // A malformed eval input fails here with the schema's reason, not as a confusing tool error
+    match schema_violations(&tool.schema(), &input) {
+        Ok(violations) if violations.is_empty() => {}
+        Ok(_) if !expect_success && matches!(error_type, None | Some("invalid_input")) => {
+            return EvalResult::Pass
+        }
+        Ok(violations) => {
+            return EvalResult::Fail(format!(
+                "input does not satisfy schema: {}",
+                violations.join("; ")
+            ))
+        }
+        Err(e) => return EvalResult::Fail(format!("Tool '{}' has an invalid schema: {}", name, e)),
+    }
+    if error_type == Some("invalid_input") {
+        return EvalResult::Fail(format!(
+            "Expected input to violate the schema of '{}'",
+            name
+        ));
+    }
+
+    let output = match tool.execute(input).await {
+        Err(e) if !expect_success => {
+            let expected = eval.then.get("error_contains").and_then(|c| c.as_str());
+            return match expected {
+                Some(expected) if !e.to_string().contains(expected) => {
+                    EvalResult::Fail(format!("Expected '{}' in the error, got: {}", expected, e))
+                }
+                _ => EvalResult::Pass,
+            };
+        }
+        Err(e) => return EvalResult::Fail(format!("Execution failed: {}", e)),
+        Ok(_) if !expect_success => {
+            return EvalResult::Fail(format!("Expected '{}' to fail, but it succeeded", name))
+        }
+        Ok(r) => r.content,
+    };
+
+    if let Some(expected) = eval.then.get("result_contains").and_then(|c| c.as_str()) {
+        if !output.contains(expected) {
+            return EvalResult::Fail(format!(
+                "Expected '{}' in result, got: {}",
+                expected, output
+            ));
+        }
+    }
+    let want_json = eval.then.get("result_is_json").and_then(|j| j.as_bool());
+    if want_json == Some(true) && serde_json::from_str::<serde_json::Value>(&output).is_err() {
+        return EvalResult::Fail(format!("Result is not valid JSON: {}", output));
+    }
+
+    EvalResult::Pass
+}
+
+/// Every way `input` breaks the JSON schema, prefixed with where in the input it happened
+fn schema_violations(
+    schema: &serde_json::Value,
+    input: &serde_json::Value,
+) -> Result<Vec<String>, String> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| e.to_string())?;
+    Ok(validator
+        .iter_errors(input)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}: {}", path, e)
+            }
+        })
+        .collect())
+}
+
+// ============================================================================
+// Hook Evals
+// ============================================================================
+
+/// A hook from a `given.hooks` entry, which is an event type (`"pre_tool_use"`) or an object
+///
+/// Objects take `type` (the event listened to; every event when absent), `name` (what it logs;
+/// defaults to the type), and `action`: `"continue"` (default), `"block"`, which blocks
+/// `for_tool` (every tool when absent) on PreToolUse, or `"error"`, which returns `Err`.
+struct ScriptedHook {
+    name: String,
+    event: Option<String>,
+    action: ScriptedAction,
+    for_tool: Option<String>,
+    fired: Arc<RwLock<Vec<String>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScriptedAction {
+    Continue,
+    Block,
+    Error,
+}
+
+impl ScriptedHook {
+    fn from_spec(spec: &serde_json::Value, fired: Arc<RwLock<Vec<String>>>) -> Result<Self> {
+        if let Some(event) = spec.as_str() {
+            return Ok(Self {
+                name: event.to_string(),
+                event: Some(event.to_string()),
+                action: ScriptedAction::Continue,
+                for_tool: None,
+                fired,
+            });
+        }
+        let event = spec.get("type").and_then(|t| t.as_str());
+        let action = match spec.get("action").and_then(|a| a.as_str()) {
+            None | Some("continue") => ScriptedAction::Continue,
+            Some("block") => ScriptedAction::Block,
+            Some("error") => ScriptedAction::Error,
+            Some(other) => anyhow::bail!(
+                "unknown hook action '{}' (expected continue, block or error)",
+                other
+            ),
+        };
+        Ok(Self {
+            name: spec
+                .get("name")
+                .and_then(|n| n.as_str())
+                .or(event)
+                .unwrap_or("hook")
+                .to_string(),
+            event: event.map(str::to_string),
+            action,
+            for_tool: spec
+                .get("for_tool")
+                .and_then(|t| t.as_str())
+                .map(str::to_string),
+            fired,
+        })
+    }
+}
+
+/// The `given.hooks`/`when.events` name of an event
+fn hook_event_type(event: &HookEvent) -> &'static str {
+    match event {
+        HookEvent::PreToolUse { .. } => "pre_tool_use",
+        HookEvent::PostToolUse { .. } => "post_tool_use",
+        HookEvent::AgentStart { .. } => "agent_start",
+        HookEvent::AgentStop { .. } => "agent_stop",
+        HookEvent::Iteration { .. } => "iteration",
+        _ => "other",
+    }
+}
+
+#[async_trait]
+impl Hook for ScriptedHook {
+    async fn on_event(&self, event: &HookEvent) -> Result<HookAction, anyhow::Error> {
+        if self
+            .event
+            .as_deref()
+            .is_some_and(|e| e != hook_event_type(event))
+        {
+            return Ok(HookAction::Continue);
+        }
+        self.fired.write().await.push(self.name.clone());
+
+        if self.action == ScriptedAction::Error {
+            anyhow::bail!("hook {} failed on {}", self.name, hook_event_type(event));
+        }
+        if let HookEvent::PreToolUse { tool_name, .. } = event {
+            let blocks = self.action == ScriptedAction::Block;
+            if blocks && self.for_tool.as_ref().is_none_or(|t| t == tool_name) {
+                return Ok(HookAction::Block(format!("Tool {} is blocked", tool_name)));
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+}
+
+/// A `when.events` entry, e.g. `{"type": "pre_tool_use", "tool": "counter", "input": {}}`
+///
+/// post_tool_use events also take the tool's `result` text.
+fn hook_event(spec: &serde_json::Value) -> Result<HookEvent> {
+    let tool_name = spec
+        .get("tool")
+        .and_then(|t| t.as_str())
+        .context("hook event is missing \"tool\"")?;
+    let input = spec
+        .get("input")
+        .cloned()
+        .unwrap_or_else(|| serde_json::json!({}));
+    match spec.get("type").and_then(|t| t.as_str()) {
+        Some("pre_tool_use") => Ok(HookEvent::PreToolUse {
+            tool_name: tool_name.into(),
+            input,
+        }),
+        Some("post_tool_use") => {
+            let result = spec.get("result").and_then(|r| r.as_str()).unwrap_or("");
+            Ok(HookEvent::PostToolUse {
+                tool_name: tool_name.into(),
+                input,
+                result: ToolResult::text(result.to_string()),
+            })
+        }
+        Some(other) => anyhow::bail!(
+            "unsupported hook event type '{}' (expected pre_tool_use or post_tool_use)",
+            other
+        ),
+        None => anyhow::bail!("hook event is missing \"type\""),
+    }
+}
+
+/// Fire `when.events` in order through the `given.hooks`, then check the action returned for
+/// the last event (`then.action`, `then.message_contains`) and the names logged (`then.fired`)
+///
+/// With `then.error_contains`, firing must instead fail with that error. The pinned semantics are
+/// that `HookRegistry::fire` stops at the first hook returning `Err` and returns that error, so
+/// later hooks and events never run; `then.fired` shows where it stopped.
+async fn run_scripted_hook_eval(eval: &Eval, events: &[serde_json::Value]) -> EvalResult {
+    let fired = Arc::new(RwLock::new(Vec::new()));
+    let registry = HookRegistry::new();
+    let specs = eval.given.get("hooks").and_then(|h| h.as_array());
+    for spec in specs.into_iter().flatten() {
+        match ScriptedHook::from_spec(spec, fired.clone()) {
+            Ok(hook) => registry.register(hook).await,
+            Err(e) => return EvalResult::Fail(format!("Invalid given.hooks entry: {}", e)),
+        }
+    }
+
+    let expected_error = eval.then.get("error_contains").and_then(|e| e.as_str());
+    let mut action = HookAction::Continue;
+    let mut error = None;
+    for spec in events {
+        let event = match hook_event(spec) {
+            Ok(event) => event,
+            Err(e) => return EvalResult::Fail(format!("Invalid when.events entry: {}", e)),
+        };
+        match registry.fire(&event).await {
+            Ok(returned) => action = returned,
+            Err(e) if expected_error.is_some() => {
+                error = Some(format!("{:#}", e));
+                break;
+            }
+            Err(e) => return EvalResult::Error(format!("Hook failed: {}", e)),
+        }
+    }
+
+    let logged = fired.read().await;
+    if let Some(expected) = eval.then.get("fired").and_then(|f| f.as_array()) {
+        let expected: Vec<&str> = expected.iter().filter_map(|n| n.as_str()).collect();
+        if *logged != expected {
+            let semantics = if expected_error.is_some() {
+                " (fire must stop at the first hook that returns Err)"
+            } else {
+                ""
+            };
+            return EvalResult::Fail(format!(
+                "Expected hooks {:?} to fire{}, got {:?}",
+                expected, semantics, *logged
+            ));
+        }
+    }
+
+    if let Some(needle) = expected_error {
+        return match error {
+            Some(error) if error.contains(needle) => EvalResult::Pass,
+            Some(error) => EvalResult::Fail(format!(
+                "Expected the hook error to contain '{}', got: {}",
+                needle, error
+            )),
+            None => EvalResult::Fail(
+                "Expected HookRegistry::fire to return the failing hook's Err, but every event \
+                 fired successfully"
+                    .to_string(),
+            ),
+        };
+    }
+
+    let (kind, message) = match &action {
+        HookAction::Block(message) => ("block", message.as_str()),
+        _ => ("continue", ""),
+    };
+    if let Some(expected) = eval.then.get("action").and_then(|a| a.as_str()) {
+        if expected != kind {
+            return EvalResult::Fail(format!("Expected {} action, got {}", expected, kind));
+        }
+    }
+    if let Some(needle) = eval.then.get("message_contains").and_then(|m| m.as_str()) {
+        if !message.contains(needle) {
+            return EvalResult::Fail(format!(
+                "Expected the hook message to contain '{}', got: '{}'",
+                needle, message
+            ));
+        }
+    }
+    EvalResult::Pass
+}
+
+/// What the runner's PreToolUse handling does with a tool call
+///
+/// mux's `HookAction` can continue or block; `ModifyInput` adds rewriting the input first.
+enum PreToolAction {
+    Continue,
+    ModifyInput(serde_json::Value),
+}
+
+/// PreToolUse hook that overwrites fields of one tool's input, e.g. forcing `b` to 1 on `divide`
+struct RewritingHook {
+    tool: String,
+    set: serde_json::Map<String, serde_json::Value>,
+    fired: AtomicUsize,
+}
+
+impl RewritingHook {
+    /// From a `given.hooks` entry: `{"type": "pre_tool_use", "action": "modify_input",
+    /// "for_tool": "divide", "set": {"b": 1}}`
+    fn from_spec(spec: &serde_json::Value) -> Result<Self> {
+        let tool = spec
+            .get("for_tool")
+            .and_then(|t| t.as_str())
+            .context("modify_input hook is missing \"for_tool\"")?;
+        let set = spec
+            .get("set")
+            .and_then(|s| s.as_object())
+            .context("modify_input hook is missing a \"set\" object")?;
+        Ok(Self {
+            tool: tool.to_string(),
+            set: set.clone(),
+            fired: AtomicUsize::new(0),
+        })
+    }
+
+    fn pre_tool_use(&self, tool_name: &str, input: &serde_json::Value) -> PreToolAction {
+        if tool_name != self.tool {
+            return PreToolAction::Continue;
+        }
+        self.fired.fetch_add(1, Ordering::SeqCst);
+        let mut input = input.clone();
+        if let Some(fields) = input.as_object_mut() {
+            for (field, value) in &self.set {
+                fields.insert(field.clone(), value.clone());
             }
         }
-        "tool-003" => {
-            // tool_invalid_input - Division by zero
-            let tool = registry.get("divide").await.unwrap();
-            let result = tool.execute(serde_json::json!({"a": 10, "b": 0})).await;
-            match result {
-                Err(_) => EvalResult::Pass,
-                Ok(_) => EvalResult::Fail("Expected error for division by zero".to_string()),
-            }
+        PreToolAction::ModifyInput(input)
+    }
+}
+
+/// Run one tool call the way the agent loop would, with the PreToolUse hook applied first
+///
+/// Returns the input the tool actually received alongside its output or error.
+async fn call_with_hook(
+    registry: &Registry,
+    hook: &RewritingHook,
+    tool_name: &str,
+    input: serde_json::Value,
+) -> (serde_json::Value, Result<String, String>) {
+    let input = match hook.pre_tool_use(tool_name, &input) {
+        PreToolAction::Continue => input,
+        PreToolAction::ModifyInput(rewritten) => rewritten,
+    };
+    let output = match registry.get(tool_name).await {
+        Some(tool) => tool
+            .execute(input.clone())
+            .await
+            .map(|r| r.content)
+            .map_err(|e| e.to_string()),
+        None => Err(format!("Tool '{}' not found", tool_name)),
+    };
+    (input, output)
+}
+
+async fn run_hook_eval(eval: &Eval) -> EvalResult {
+    if let Some(events) = eval.when.get("events").and_then(|e| e.as_array()) {
+        return run_scripted_hook_eval(eval, events).await;
+    }
+
+    match eval.id.as_str() {
+        "hook-005" | "hook-006" => {
+            // These require agent execution, skip for now
+            EvalResult::Skip("Requires agent execution".to_string())
         }
-        "tool-004" => {
-            // tool_result_string - Greet returns string with name
-            let tool = registry.get("greet").await.unwrap();
-            let result = tool.execute(serde_json::json!({"name": "World"})).await;
-            match result {
-                Ok(r) => {
-                    if r.content.contains("World") {
-                        EvalResult::Pass
-                    } else {
-                        EvalResult::Fail(format!("Expected 'World' in result, got: {}", r.content))
-                    }
+        "hook-007" => {
+            // hook_modify_input - PreToolUse hook rewrites the input the tool receives
+            let spec = eval
+                .given
+                .get("hooks")
+                .and_then(|h| h.as_array())
+                .and_then(|h| h.first());
+            let hook = match spec.map(RewritingHook::from_spec) {
+                Some(Ok(hook)) => hook,
+                Some(Err(e)) => return EvalResult::Fail(format!("Invalid given.hooks: {}", e)),
+                None => return EvalResult::Fail("given.hooks is empty".to_string()),
+            };
+            let (registry, _) = match tool_registry(given_tools(eval), &[]).await {
+                Ok(tools) => tools,
+                Err(e) => return EvalResult::Fail(format!("Invalid given.tools entry: {}", e)),
+            };
+            let tool_name = eval.when.get("tool").and_then(|t| t.as_str()).unwrap_or("");
+            let input = eval
+                .when
+                .get("input")
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!({}));
+
+            let (received, output) = call_with_hook(&registry, &hook, tool_name, input).await;
+
+            if hook.fired.load(Ordering::SeqCst) == 0 {
+                return EvalResult::Fail("PreToolUse hook did not fire".to_string());
+            }
+            if let Some(expected) = eval.then.get("tool_input") {
+                if &received != expected {
+                    return EvalResult::Fail(format!(
+                        "Tool received {}, expected the rewritten {}",
+                        received, expected
+                    ));
                 }
-                Err(e) => EvalResult::Fail(format!("Execution failed: {}", e)),
             }
-        }
-        "tool-005" => {
-            // tool_result_json - GetInfo returns valid JSON
-            let tool = registry.get("get_info").await.unwrap();
-            let result = tool.execute(serde_json::json!({})).await;
-            match result {
-                Ok(r) => {
-                    if serde_json::from_str::<serde_json::Value>(&r.content).is_ok() {
-                        EvalResult::Pass
-                    } else {
-                        EvalResult::Fail(format!("Result is not valid JSON: {}", r.content))
+            match output {
+                Ok(content) => match eval.then.get("result_contains").and_then(|r| r.as_str()) {
+                    Some(needle) if !content.contains(needle) => {
+                        EvalResult::Fail(format!("Expected '{}', got: {}", needle, content))
                     }
-                }
-                Err(e) => EvalResult::Fail(format!("Execution failed: {}", e)),
+                    _ => EvalResult::Pass,
+                },
+                Err(e) => EvalResult::Fail(format!("Tool failed on the rewritten input: {}", e)),
             }
         }
-        _ => EvalResult::Skip(format!("Unknown tool eval: {}", eval.id)),
+        _ => EvalResult::Skip(format!("Unknown hook eval: {}", eval.id)),
     }
 }
 
 // ============================================================================
-// Hook Evals
+// Agent Loop - Minimal tool-calling loop over an LlmClient and Registry
 // ============================================================================
 
-struct LoggingHook {
-    events: Arc<RwLock<Vec<String>>>,
+/// A tool invocation made during the agent loop, including what was fed back
+#[derive(Debug, Clone, Serialize)]
+struct ToolCallRecord {
+    name: String,
+    input: serde_json::Value,
+    output: String,
+    is_error: bool,
 }
 
-impl LoggingHook {
-    fn new() -> (Self, Arc<RwLock<Vec<String>>>) {
-        let events = Arc::new(RwLock::new(Vec::new()));
-        (
-            Self {
-                events: events.clone(),
-            },
-            events,
-        )
-    }
+/// Result of driving the model until it stops asking for tools
+struct AgentRun {
+    final_text: String,
+    tool_calls: Vec<ToolCallRecord>,
+    /// Tokens summed over every turn, including tool-call turns
+    usage: TokenUsage,
+    /// Model requests made before it stopped asking for tools
+    iterations: usize,
+    /// Tool failures the model had to recover from (execute errors, unknown tools)
+    errors: Vec<String>,
+    /// The full conversation, ending with the model's final reply
+    messages: Vec<Message>,
 }
 
-#[async_trait]
-impl Hook for LoggingHook {
-    async fn on_event(&self, event: &HookEvent) -> Result<HookAction, anyhow::Error> {
-        let msg = match event {
-            HookEvent::PreToolUse { tool_name, .. } => format!("pre_tool_use:{}", tool_name),
-            HookEvent::PostToolUse { tool_name, .. } => format!("post_tool_use:{}", tool_name),
-            HookEvent::AgentStart { agent_id, .. } => format!("agent_start:{}", agent_id),
-            HookEvent::AgentStop { agent_id, .. } => format!("agent_stop:{}", agent_id),
-            HookEvent::Iteration { agent_id, iteration } => {
-                format!("iteration:{}:{}", agent_id, iteration)
-            }
-            // Handle other hook events generically
-            _ => format!("{:?}", event),
+async fn run_agent_loop(
+    provider: &str,
+    model: &str,
+    registry: &Registry,
+    tool_names: &[&str],
+    prompt: &str,
+    max_iterations: usize,
+    params: Option<&serde_json::Value>,
+) -> Result<AgentRun> {
+    let client = llm_client_for(provider)?;
+    let mut tools = Vec::new();
+    for name in tool_names {
+        let tool = registry
+            .get(name)
+            .await
+            .with_context(|| format!("Tool '{}' not registered", name))?;
+        tools.push(ToolDefinition {
+            name: tool.name().to_string(),
+            description: tool.description().to_string(),
+            input_schema: tool.schema(),
+        });
+    }
+
+    let mut messages = vec![Message {
+        role: Role::User,
+        content: vec![ContentBlock::Text {
+            text: prompt.to_string(),
+        }],
+    }];
+    let mut tool_calls = Vec::new();
+    let mut errors = Vec::new();
+    let mut usage = TokenUsage::default();
+
+    for iteration in 1..=max_iterations {
+        let mut request = Request {
+            model: model.to_string(),
+            messages: messages.clone(),
+            tools: tools.clone(),
+            max_tokens: Some(1024),
+            ..Default::default()
         };
-        self.events.write().await.push(msg);
-        Ok(HookAction::Continue)
+        apply_request_params(&mut request, params);
+        let response = send_request(&client, provider, &request, false).await?;
+        usage += TokenUsage::of(&response);
+
+        let mut results = Vec::new();
+        for block in &response.content {
+            if let ContentBlock::ToolUse { id, name, input } = block {
+                // Tool errors are fed back to the model rather than aborting the loop
+                let (output, is_error) = match registry.get(name).await {
+                    Some(tool) => match tool.execute(input.clone()).await {
+                        Ok(r) => (r.content, r.is_error),
+                        Err(e) => {
+                            errors.push(format!("{} failed: {}", name, e));
+                            (format!("Error: {}", e), true)
+                        }
+                    },
+                    None => {
+                        errors.push(format!("unknown tool '{}'", name));
+                        (format!("Error: unknown tool '{}'", name), true)
+                    }
+                };
+                tool_calls.push(ToolCallRecord {
+                    name: name.clone(),
+                    input: input.clone(),
+                    output: output.clone(),
+                    is_error,
+                });
+                results.push(ContentBlock::ToolResult {
+                    tool_use_id: id.clone(),
+                    content: output,
+                    is_error,
+                });
+            }
+        }
+
+        let final_text = collect_text(&response.content);
+        messages.push(Message {
+            role: Role::Assistant,
+            content: response.content,
+        });
+
+        if results.is_empty() {
+            return Ok(AgentRun {
+                final_text,
+                tool_calls,
+                usage,
+                iterations: iteration,
+                errors,
+                messages,
+            });
+        }
+
+        messages.push(Message {
+            role: Role::User,
+            content: results,
+        });
     }
+
+    anyhow::bail!("Agent loop exceeded {} iterations", max_iterations)
 }
 
-struct BlockingHook {
-    block_tool: String,
+/// Turn cap for the tool-calling agent evals, so a model that never stops can't run up a bill
+const AGENT_LOOP_MAX_ITERATIONS: usize = 10;
+
+/// Builtins the tool-calling agent evals register alongside their `given.tools`
+const AGENT_LOOP_BUILTINS: &[&str] = &["add", "divide", "greet", "counter"];
+
+/// Check recorded tool calls against `then.tool_called` (one name), `then.tools_called`
+/// (names in that order) and `then.tools_called_contains` (names in any order)
+fn check_tool_calls(eval: &Eval, calls: &[ToolCallRecord]) -> EvalResult {
+    let called: Vec<&str> = calls.iter().map(|c| c.name.as_str()).collect();
+    let names = |key: &str| -> Vec<&str> {
+        eval.then
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|n| n.as_str()).collect())
+            .unwrap_or_default()
+    };
+
+    if let Some(name) = eval.then.get("tool_called").and_then(|t| t.as_str()) {
+        if !called.contains(&name) {
+            return EvalResult::Fail(format!("Expected a call to '{}', got {:?}", name, called));
+        }
+    }
+
+    let sequence = names("tools_called");
+    let mut remaining = called.iter();
+    if !sequence
+        .iter()
+        .all(|name| remaining.any(|called| called == name))
+    {
+        return EvalResult::Fail(format!(
+            "Expected calls {:?} in order, got {:?}",
+            sequence, called
+        ));
+    }
+
+    let missing: Vec<&str> = names("tools_called_contains")
+        .into_iter()
+        .filter(|name| !called.contains(name))
+        .collect();
+    if !missing.is_empty() {
+        return EvalResult::Fail(format!("Expected calls to {:?}, got {:?}", missing, called));
+    }
+
+    EvalResult::Pass
 }
 
-#[async_trait]
-impl Hook for BlockingHook {
-    async fn on_event(&self, event: &HookEvent) -> Result<HookAction, anyhow::Error> {
-        if let HookEvent::PreToolUse { tool_name, .. } = event {
-            if tool_name == &self.block_tool {
-                return Ok(HookAction::Block(format!("Tool {} is blocked", tool_name)));
+/// Check the shape of the conversation: `then.stop_reason: "end_turn"` needs the last turn to
+/// be a plain assistant reply, and `then.parallel: true` needs every `tools_called_contains`
+/// tool requested in a single assistant turn
+fn check_turns(eval: &Eval, messages: &[Message]) -> EvalResult {
+    let tool_uses = |message: &Message| -> Vec<String> {
+        message
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolUse { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    match eval.then.get("stop_reason").and_then(|r| r.as_str()) {
+        Some("end_turn") => match messages.last() {
+            Some(last)
+                if matches!(last.role, Role::Assistant)
+                    && tool_uses(last).is_empty()
+                    && !collect_text(&last.content).trim().is_empty() => {}
+            _ => {
+                return EvalResult::Fail(
+                    "Loop did not end on a plain assistant reply (end_turn)".to_string(),
+                )
             }
+        },
+        Some(other) => return EvalResult::Fail(format!("Unsupported stop_reason: {}", other)),
+        None => {}
+    }
+
+    let parallel = eval
+        .then
+        .get("parallel")
+        .and_then(|p| p.as_bool())
+        .unwrap_or(false);
+    if parallel {
+        let wanted: Vec<&str> = eval
+            .then
+            .get("tools_called_contains")
+            .and_then(|t| t.as_array())
+            .map(|a| a.iter().filter_map(|n| n.as_str()).collect())
+            .unwrap_or_default();
+        let together = messages
+            .iter()
+            .filter(|m| matches!(m.role, Role::Assistant))
+            .map(tool_uses)
+            .any(|names| wanted.iter().all(|w| names.iter().any(|n| n == w)));
+        if !together {
+            return EvalResult::Fail(format!(
+                "Expected {:?} to be requested in one assistant turn",
+                wanted
+            ));
         }
-        Ok(HookAction::Continue)
     }
+
+    EvalResult::Pass
 }
 
-struct NamedHook {
-    name: String,
-    events: Arc<RwLock<Vec<String>>>,
+/// Prompt and completion tokens reported by the provider
+#[derive(Debug, Default, Clone, Copy)]
+struct TokenUsage {
+    prompt: u64,
+    completion: u64,
 }
 
-#[async_trait]
-impl Hook for NamedHook {
-    async fn on_event(&self, _event: &HookEvent) -> Result<HookAction, anyhow::Error> {
-        self.events.write().await.push(self.name.clone());
-        Ok(HookAction::Continue)
+impl TokenUsage {
+    fn of(response: &Response) -> Self {
+        Self {
+            prompt: u64::from(response.usage.input_tokens),
+            completion: u64::from(response.usage.output_tokens),
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.prompt + self.completion
     }
 }
 
-async fn run_hook_eval(eval: &Eval) -> EvalResult {
-    match eval.id.as_str() {
-        "hook-001" => {
-            // hook_pre_tool_fires - PreToolUse hook fires before execution
-            let registry = HookRegistry::new();
-            let (hook, events) = LoggingHook::new();
-            registry.register(hook).await;
-
-            let event = HookEvent::PreToolUse {
-                tool_name: "counter".into(),
-                input: serde_json::json!({}),
-            };
-            let _ = registry.fire(&event).await;
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt += other.prompt;
+        self.completion += other.completion;
+    }
+}
 
-            let logged = events.read().await;
-            if logged.iter().any(|e| e.starts_with("pre_tool_use:")) {
-                EvalResult::Pass
-            } else {
-                EvalResult::Fail("PreToolUse hook did not fire".to_string())
-            }
-        }
-        "hook-002" => {
-            // hook_post_tool_fires - PostToolUse hook fires after execution
-            let registry = HookRegistry::new();
-            let (hook, events) = LoggingHook::new();
-            registry.register(hook).await;
+/// Record token usage and fail an otherwise passing eval that exceeds `then.max_total_tokens`
+fn enforce_token_budget(
+    eval: &Eval,
+    result: EvalResult,
+    usage: TokenUsage,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let total_tokens = usage.total();
+    artifacts.total_tokens = Some(total_tokens);
+    artifacts.add_metric("prompt_tokens", usage.prompt as f64);
+    artifacts.add_metric("completion_tokens", usage.completion as f64);
 
-            let event = HookEvent::PostToolUse {
-                tool_name: "counter".into(),
-                input: serde_json::json!({}),
-                result: ToolResult::text("done"),
-            };
-            let _ = registry.fire(&event).await;
+    let budget = match eval.then.get("max_total_tokens").and_then(|m| m.as_u64()) {
+        Some(b) => b,
+        None => return result,
+    };
 
-            let logged = events.read().await;
-            if logged.iter().any(|e| e.starts_with("post_tool_use:")) {
-                EvalResult::Pass
-            } else {
-                EvalResult::Fail("PostToolUse hook did not fire".to_string())
-            }
-        }
-        "hook-003" => {
-            // hook_block_tool - PreToolUse hook can block execution
-            let registry = HookRegistry::new();
-            registry
-                .register(BlockingHook {
-                    block_tool: "dangerous".into(),
-                })
-                .await;
+    match result {
+        EvalResult::Pass if total_tokens > budget => EvalResult::Fail(format!(
+            "Correct but over token budget: used {} tokens, budget {}",
+            total_tokens, budget
+        )),
+        other => other,
+    }
+}
 
-            let event = HookEvent::PreToolUse {
-                tool_name: "dangerous".into(),
-                input: serde_json::json!({}),
-            };
-            let action = registry.fire(&event).await.unwrap();
+/// Fail an otherwise passing LLM eval whose single call exceeds `then.max_tokens` or
+/// `then.max_cost_usd`
+///
+/// Either limit needs usage from the provider, so a response without any fails rather than
+/// passing unchecked.
+fn enforce_usage_limits(
+    eval: &Eval,
+    result: EvalResult,
+    model: &str,
+    usage: TokenUsage,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let cost = cost_usd(model, usage).filter(|_| usage.total() > 0);
+    if let Some(cost) = cost {
+        artifacts.add_metric("cost_usd", cost);
+    }
 
-            if matches!(action, HookAction::Block(_)) {
-                EvalResult::Pass
-            } else {
-                EvalResult::Fail("Expected Block action".to_string())
-            }
-        }
-        "hook-004" => {
-            // hook_chain_order - Multiple hooks fire in registration order
-            let registry = HookRegistry::new();
-            let events = Arc::new(RwLock::new(Vec::new()));
+    let max_tokens = eval.then.get("max_tokens").and_then(|m| m.as_u64());
+    let max_cost = eval.then.get("max_cost_usd").and_then(|m| m.as_f64());
+    if !matches!(result, EvalResult::Pass) || (max_tokens.is_none() && max_cost.is_none()) {
+        return result;
+    }
+    if usage.total() == 0 {
+        return EvalResult::Fail("usage not reported by provider".to_string());
+    }
 
-            registry
-                .register(NamedHook {
-                    name: "first".into(),
-                    events: events.clone(),
-                })
-                .await;
-            registry
-                .register(NamedHook {
-                    name: "second".into(),
-                    events: events.clone(),
-                })
-                .await;
+    if let Some(max) = max_tokens {
+        if usage.total() > max {
+            return EvalResult::Fail(format!(
+                "Used {} tokens ({} in / {} out), limit {}",
+                usage.total(),
+                usage.prompt,
+                usage.completion,
+                max
+            ));
+        }
+    }
+    match (max_cost, cost) {
+        (Some(_), None) => EvalResult::Fail(format!(
+            "No price known for {} to check max_cost_usd",
+            model
+        )),
+        (Some(max), Some(cost)) if cost > max => {
+            EvalResult::Fail(format!("Cost ${:.6} over limit ${:.6}", cost, max))
+        }
+        _ => result,
+    }
+}
 
-            let event = HookEvent::PreToolUse {
-                tool_name: "test".into(),
-                input: serde_json::json!({}),
-            };
-            let _ = registry.fire(&event).await;
+/// Fail an otherwise passing eval with `then.clean_run` if the agent loop hit any errors
+fn enforce_clean_run(eval: &Eval, result: EvalResult, artifacts: &EvalArtifacts) -> EvalResult {
+    let clean_run = eval
+        .then
+        .get("clean_run")
+        .and_then(|c| c.as_bool())
+        .unwrap_or(false);
 
-            let logged = events.read().await;
-            if logged.len() == 2 && logged[0] == "first" && logged[1] == "second" {
-                EvalResult::Pass
-            } else {
-                EvalResult::Fail(format!("Expected ['first', 'second'], got {:?}", *logged))
-            }
+    match result {
+        EvalResult::Pass if clean_run && !artifacts.loop_errors.is_empty() => {
+            EvalResult::Fail(format!(
+                "Answer passed but the agent loop hit {} error(s): {}",
+                artifacts.loop_errors.len(),
+                artifacts.loop_errors.join("; ")
+            ))
         }
-        "hook-005" | "hook-006" => {
-            // These require agent execution, skip for now
-            EvalResult::Skip("Requires agent execution".to_string())
+        other => other,
+    }
+}
+
+/// Render a conversation as a role-labelled dialog, so the judge can tell which turn said what
+fn render_dialog(messages: &[Message]) -> String {
+    let mut turns = Vec::new();
+    for message in messages {
+        let role = if matches!(message.role, Role::User) {
+            "USER"
+        } else {
+            "ASSISTANT"
+        };
+        for block in &message.content {
+            let text = match block {
+                ContentBlock::Text { text } => text.clone(),
+                ContentBlock::ToolUse { name, input, .. } => {
+                    format!("[tool call] {}({})", name, input)
+                }
+                ContentBlock::ToolResult {
+                    content, is_error, ..
+                } => {
+                    let marker = if *is_error { "error" } else { "ok" };
+                    format!("[tool result: {}] {}", marker, content)
+                }
+                _ => continue,
+            };
+            turns.push(format!("{}: {}", role, text));
         }
-        _ => EvalResult::Skip(format!("Unknown hook eval: {}", eval.id)),
     }
+    turns.join("\n")
+}
+
+/// Render recorded tool calls, including their results, for the judge prompt
+fn format_tool_trace(calls: &[ToolCallRecord]) -> String {
+    if calls.is_empty() {
+        return "(no tool calls)".to_string();
+    }
+
+    calls
+        .iter()
+        .map(|c| {
+            let marker = if c.is_error { "error" } else { "ok" };
+            format!("- {}({}) -> [{}] {}", c.name, c.input, marker, c.output)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 // ============================================================================
 // Agent Evals - Use Judge to evaluate agent task completion
 // ============================================================================
 
-async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
+/// Default for --agent-model
+const DEFAULT_AGENT_MODEL: &str = "claude-sonnet-4-20250514";
+
+/// Model from --agent-model, set once at startup
+static AGENT_MODEL: OnceLock<String> = OnceLock::new();
+
+/// Model an agent eval runs on: its `given.model`, else --agent-model
+fn agent_model(eval: &Eval) -> &str {
+    eval.given
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or_else(|| {
+            AGENT_MODEL
+                .get()
+                .map_or(DEFAULT_AGENT_MODEL, String::as_str)
+        })
+}
+
+async fn run_agent_eval(
+    eval: &Eval,
+    opts: &RunOptions,
+    judge: Option<&JudgePanel>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let model = agent_model(eval);
+    let provider = provider_for_model(model);
+
     // Check if we have API key for agent execution
-    if std::env::var("ANTHROPIC_API_KEY").is_err() {
-        return EvalResult::Skip("ANTHROPIC_API_KEY not set".to_string());
+    if let Some(reason) = provider_unavailable(provider) {
+        return EvalResult::Skip(reason);
     }
 
-    let judge = match judge {
-        Some(j) => j,
-        None => return EvalResult::Skip("Judge not available for agent eval".to_string()),
+    // then.judge_model and then.language swap in a judge for just this eval
+    let language = eval.then.get("language").and_then(|l| l.as_str());
+    let eval_judge;
+    let judge = match (eval.then.get("judge_model").and_then(|m| m.as_str()), judge) {
+        (Some(model), _) => match create_judge(provider_for_model(model), model) {
+            Ok(j) => {
+                let inherited = judge.and_then(|g| g.language().cloned());
+                let (max_tokens, temperature) = judge
+                    .and_then(|g| g.judges.first())
+                    .map_or((JUDGE_MAX_TOKENS, None), |g| (g.max_tokens, g.temperature));
+                eval_judge = JudgePanel::new(vec![j])
+                    .with_language(language.map(str::to_string).or(inherited))
+                    .with_samples(judge.map_or(1, |g| g.samples))
+                    .with_request_params(max_tokens, temperature)
+                    .with_tie(judge.map(|g| g.tie).unwrap_or_default());
+                &eval_judge
+            }
+            Err(e) => return EvalResult::Skip(format!("Judge {} not available: {}", model, e)),
+        },
+        (None, Some(j)) => match language {
+            Some(language) => {
+                eval_judge = j.clone().with_language(Some(language.to_string()));
+                &eval_judge
+            }
+            None => j,
+        },
+        (None, None) => return EvalResult::Skip("Judge not available for agent eval".to_string()),
+    };
+
+    let params = match request_params(eval, provider, opts.strict_params) {
+        Ok(p) => p,
+        Err(e) => return EvalResult::Fail(format!("Invalid request params: {}", e)),
     };
 
     // Get task from eval
@@ -796,12 +4024,25 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
         .and_then(|e| e.as_str())
         .unwrap_or("Task should be completed correctly");
 
+    let include_tool_trace = eval
+        .then
+        .get("include_tool_trace")
+        .and_then(|t| t.as_bool())
+        .unwrap_or(false);
+
+    // Judge the whole conversation instead of only the final reply
+    let judge_transcript = eval
+        .then
+        .get("judge_transcript")
+        .and_then(|t| t.as_bool())
+        .unwrap_or(false);
+
     match eval.id.as_str() {
         "agent-001" => {
             // agent_simple_task - Agent completes a simple task
-            let client = AnthropicClient::from_env().unwrap();
-            let request = Request {
-                model: "claude-sonnet-4-20250514".to_string(),
+            let client = llm_client_for(provider).unwrap();
+            let mut request = Request {
+                model: model.to_string(),
                 messages: vec![Message {
                     role: Role::User,
                     content: vec![ContentBlock::Text {
@@ -811,9 +4052,16 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
                 max_tokens: Some(100),
                 ..Default::default()
             };
+            apply_request_params(&mut request, params.as_ref());
 
-            match client.create_message(&request).await {
+            match send_request(&client, provider, &request, false).await {
                 Ok(response) => {
+                    if opts.capture_thinking {
+                        artifacts
+                            .thinking
+                            .extend(collect_thinking(&response.content));
+                    }
+
                     let output = response
                         .content
                         .iter()
@@ -828,42 +4076,87 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
                         .join("");
 
                     // Use judge to evaluate
-                    match judge
-                        .evaluate(
-                            "Answer: What is 2 + 2?",
-                            &output,
-                            "Response should contain the number 4",
-                        )
-                        .await
-                    {
-                        Ok((passed, reason)) => {
-                            if passed {
-                                EvalResult::Pass
-                            } else {
-                                EvalResult::Fail(reason)
-                            }
-                        }
-                        Err(e) => EvalResult::Fail(format!("Judge error: {}", e)),
-                    }
+                    let result = judge_output(
+                        judge,
+                        "Answer: What is 2 + 2?",
+                        &output,
+                        "Response should contain the number 4",
+                        None,
+                        artifacts,
+                    )
+                    .await;
+                    enforce_token_budget(eval, result, TokenUsage::of(&response), artifacts)
                 }
-                Err(e) => EvalResult::Fail(format!("LLM request failed: {}", e)),
+                Err(e) => EvalResult::Error(format!("LLM request failed: {}", e)),
             }
         }
         "agent-002" | "agent-004" | "agent-005" | "agent-006" => {
-            // These evals require full agent loop with tool registration:
+            // Full agent loop over the test tools plus whatever the eval declares:
             // agent-002: Agent uses tools when needed
             // agent-004: Agent stops on end_turn
             // agent-005: Agent calls multiple tools in sequence
             // agent-006: Agent calls multiple tools in parallel
-            EvalResult::Skip("Requires full agent loop with tools".to_string())
+            let (registry, names) =
+                match tool_registry(given_tools(eval), AGENT_LOOP_BUILTINS).await {
+                    Ok(tools) => tools,
+                    Err(e) => return EvalResult::Fail(format!("Invalid given.tools entry: {}", e)),
+                };
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            let prompt = eval
+                .when
+                .get("prompt")
+                .and_then(|p| p.as_str())
+                .unwrap_or(task);
+
+            let run = match run_agent_loop(
+                provider,
+                model,
+                &registry,
+                &names,
+                prompt,
+                AGENT_LOOP_MAX_ITERATIONS,
+                params.as_ref(),
+            )
+            .await
+            {
+                Ok(run) => run,
+                Err(e) => return EvalResult::Error(format!("Agent loop failed: {}", e)),
+            };
+            artifacts.tool_calls = run.tool_calls.clone();
+            artifacts.loop_errors = run.errors.clone();
+            artifacts.add_metric("iterations", run.iterations as f64);
+
+            // Tool use is asserted structurally; the judge only grades the wording, if asked to
+            let mut result = check_tool_calls(eval, &run.tool_calls);
+            if matches!(result, EvalResult::Pass) {
+                result = check_turns(eval, &run.messages);
+            }
+            if matches!(result, EvalResult::Pass) && eval.then.get("expect").is_some() {
+                let tool_trace = include_tool_trace.then(|| format_tool_trace(&run.tool_calls));
+                let output = if judge_transcript {
+                    render_dialog(&run.messages)
+                } else {
+                    run.final_text
+                };
+                result = judge_output(
+                    judge,
+                    prompt,
+                    &output,
+                    criteria,
+                    tool_trace.as_deref(),
+                    artifacts,
+                )
+                .await;
+            }
+            enforce_token_budget(eval, result, run.usage, artifacts)
         }
         "agent-003" => {
             // agent_multi_turn - Agent maintains context across turns
-            let client = AnthropicClient::from_env().unwrap();
+            let client = llm_client_for(provider).unwrap();
 
             // First turn
-            let request1 = Request {
-                model: "claude-sonnet-4-20250514".to_string(),
+            let mut request1 = Request {
+                model: model.to_string(),
                 messages: vec![Message {
                     role: Role::User,
                     content: vec![ContentBlock::Text {
@@ -873,11 +4166,17 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
                 max_tokens: Some(100),
                 ..Default::default()
             };
+            apply_request_params(&mut request1, params.as_ref());
 
-            let response1 = match client.create_message(&request1).await {
+            let response1 = match send_request(&client, provider, &request1, false).await {
                 Ok(r) => r,
-                Err(e) => return EvalResult::Fail(format!("First turn failed: {}", e)),
+                Err(e) => return EvalResult::Error(format!("First turn failed: {}", e)),
             };
+            if opts.capture_thinking {
+                artifacts
+                    .thinking
+                    .extend(collect_thinking(&response1.content));
+            }
 
             let assistant_reply = response1
                 .content
@@ -893,8 +4192,8 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
                 .join("");
 
             // Second turn with context
-            let request2 = Request {
-                model: "claude-sonnet-4-20250514".to_string(),
+            let mut request2 = Request {
+                model: model.to_string(),
                 messages: vec![
                     Message {
                         role: Role::User,
@@ -918,87 +4217,271 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
                 max_tokens: Some(100),
                 ..Default::default()
             };
+            apply_request_params(&mut request2, params.as_ref());
+
+            match send_request(&client, provider, &request2, false).await {
+                Ok(response) => {
+                    if opts.capture_thinking {
+                        artifacts
+                            .thinking
+                            .extend(collect_thinking(&response.content));
+                    }
+
+                    let output = if judge_transcript {
+                        let mut dialog = request2.messages.clone();
+                        dialog.push(Message {
+                            role: Role::Assistant,
+                            content: response.content.clone(),
+                        });
+                        render_dialog(&dialog)
+                    } else {
+                        collect_text(&response.content)
+                    };
+
+                    let result = judge_output(
+                        judge,
+                        "Remember the name Alice from context, then answer 'What is my name?'",
+                        &output,
+                        "Response should mention Alice",
+                        None,
+                        artifacts,
+                    )
+                    .await;
+                    let mut usage = TokenUsage::of(&response1);
+                    usage += TokenUsage::of(&response);
+                    enforce_token_budget(eval, result, usage, artifacts)
+                }
+                Err(e) => EvalResult::Error(format!("Second turn failed: {}", e)),
+            }
+        }
+        "agent-007" => {
+            // agent_reports_tool_error - Tool error reaches the model as a ToolResult
+            // given.tools names fetch_report, whose builtin always fails
+            let (registry, _) = match tool_registry(given_tools(eval), &[]).await {
+                Ok(tools) => tools,
+                Err(e) => return EvalResult::Fail(format!("Invalid given.tools entry: {}", e)),
+            };
+
+            let run = match run_agent_loop(
+                provider,
+                model,
+                &registry,
+                &["fetch_report"],
+                task,
+                5,
+                params.as_ref(),
+            )
+            .await
+            {
+                Ok(run) => run,
+                Err(e) => return EvalResult::Error(format!("Agent loop failed: {}", e)),
+            };
+            artifacts.tool_calls = run.tool_calls.clone();
+            artifacts.loop_errors = run.errors.clone();
+            artifacts.add_metric("iterations", run.iterations as f64);
+
+            // The error must have been fed back as an error ToolResult before judging
+            if !run
+                .tool_calls
+                .iter()
+                .any(|c| c.is_error && c.output.contains("Disk quota exceeded"))
+            {
+                return EvalResult::Fail(format!(
+                    "Tool error was not returned as an error ToolResult: {}",
+                    format_tool_trace(&run.tool_calls)
+                ));
+            }
+
+            let tool_trace = include_tool_trace.then(|| format_tool_trace(&run.tool_calls));
+            let output = if judge_transcript {
+                render_dialog(&run.messages)
+            } else {
+                run.final_text
+            };
+            let result = judge_output(
+                judge,
+                task,
+                &output,
+                criteria,
+                tool_trace.as_deref(),
+                artifacts,
+            )
+            .await;
+            let result = enforce_clean_run(eval, result, artifacts);
+            enforce_token_budget(eval, result, run.usage, artifacts)
+        }
+        _ => {
+            // Generic agent eval using task/criteria from eval definition
+            let client = llm_client_for(provider).unwrap();
+            let declared = eval
+                .given
+                .get("tools")
+                .and_then(|t| t.as_array())
+                .filter(|t| !t.is_empty());
+
+            let (output, tool_trace, usage) = match declared {
+                Some(specs) => {
+                    // Tools listed in the eval run through the agent loop, and only those
+                    let (registry, names) = match tool_registry(specs, &[]).await {
+                        Ok(tools) => tools,
+                        Err(e) => {
+                            return EvalResult::Fail(format!("Invalid given.tools entry: {}", e))
+                        }
+                    };
+                    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                    let max_iterations = eval
+                        .given
+                        .get("max_iterations")
+                        .and_then(|m| m.as_u64())
+                        .unwrap_or(5) as usize;
+
+                    let run = match run_agent_loop(
+                        provider,
+                        model,
+                        &registry,
+                        &names,
+                        task,
+                        max_iterations,
+                        params.as_ref(),
+                    )
+                    .await
+                    {
+                        Ok(run) => run,
+                        Err(e) => return EvalResult::Error(format!("Agent loop failed: {}", e)),
+                    };
+                    let tool_trace = include_tool_trace.then(|| format_tool_trace(&run.tool_calls));
+                    artifacts.tool_calls = run.tool_calls;
+                    artifacts.loop_errors = run.errors;
+                    artifacts.add_metric("iterations", run.iterations as f64);
+                    let output = if judge_transcript {
+                        render_dialog(&run.messages)
+                    } else {
+                        run.final_text
+                    };
+                    (output, tool_trace, run.usage)
+                }
+                None => {
+                    let mut request = Request {
+                        model: model.to_string(),
+                        messages: vec![Message {
+                            role: Role::User,
+                            content: vec![ContentBlock::Text {
+                                text: task.to_string(),
+                            }],
+                        }],
+                        max_tokens: Some(500),
+                        ..Default::default()
+                    };
+                    apply_request_params(&mut request, params.as_ref());
+
+                    let response = match send_request(&client, provider, &request, false).await {
+                        Ok(r) => r,
+                        Err(e) => return EvalResult::Error(format!("LLM request failed: {}", e)),
+                    };
+                    if opts.capture_thinking {
+                        artifacts
+                            .thinking
+                            .extend(collect_thinking(&response.content));
+                    }
 
-            match client.create_message(&request2).await {
-                Ok(response) => {
-                    let output = response
-                        .content
-                        .iter()
-                        .filter_map(|b| {
-                            if let ContentBlock::Text { text } = b {
-                                Some(text.as_str())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
+                    // Only grade the approach when the eval explicitly asks for it
+                    let tool_trace =
+                        include_tool_trace.then(|| summarize_tool_calls(&response.content));
+                    (
+                        collect_text(&response.content),
+                        tool_trace,
+                        TokenUsage::of(&response),
+                    )
+                }
+            };
 
-                    match judge
-                        .evaluate(
-                            "Remember the name Alice from context, then answer 'What is my name?'",
+            let result = match eval.then.get("subgoals").and_then(|s| s.as_array()) {
+                Some(subgoals) => {
+                    let min_score = eval
+                        .then
+                        .get("min_score")
+                        .and_then(|m| m.as_f64())
+                        .unwrap_or(opts.min_score);
+                    judge_subgoals(
+                        judge,
+                        task,
+                        &output,
+                        subgoals,
+                        min_score,
+                        tool_trace.as_deref(),
+                        artifacts,
+                    )
+                    .await
+                }
+                None => match eval.then.get("rubric").and_then(|r| r.as_str()) {
+                    // then.min_score is on the judge's 0-10 scale here, not the 0.0-1.0 of sub-goals
+                    Some(rubric) => {
+                        let min_score = eval
+                            .then
+                            .get("min_score")
+                            .and_then(|m| m.as_f64())
+                            .unwrap_or(RUBRIC_MIN_SCORE);
+                        judge_rubric(
+                            judge,
+                            task,
                             &output,
-                            "Response should mention Alice",
+                            rubric,
+                            min_score,
+                            tool_trace.as_deref(),
+                            artifacts,
                         )
                         .await
-                    {
-                        Ok((passed, reason)) => {
-                            if passed {
-                                EvalResult::Pass
-                            } else {
-                                EvalResult::Fail(reason)
-                            }
-                        }
-                        Err(e) => EvalResult::Fail(format!("Judge error: {}", e)),
                     }
-                }
-                Err(e) => EvalResult::Fail(format!("Second turn failed: {}", e)),
-            }
-        }
-        _ => {
-            // Generic agent eval using task/criteria from eval definition
-            let client = AnthropicClient::from_env().unwrap();
-            let request = Request {
-                model: "claude-sonnet-4-20250514".to_string(),
-                messages: vec![Message {
-                    role: Role::User,
-                    content: vec![ContentBlock::Text {
-                        text: task.to_string(),
-                    }],
-                }],
-                max_tokens: Some(500),
-                ..Default::default()
+                    None => {
+                        judge_output(
+                            judge,
+                            task,
+                            &output,
+                            criteria,
+                            tool_trace.as_deref(),
+                            artifacts,
+                        )
+                        .await
+                    }
+                },
             };
+            let result = enforce_clean_run(eval, result, artifacts);
+            enforce_token_budget(eval, result, usage, artifacts)
+        }
+    }
+}
 
-            match client.create_message(&request).await {
-                Ok(response) => {
-                    let output = response
-                        .content
-                        .iter()
-                        .filter_map(|b| {
-                            if let ContentBlock::Text { text } = b {
-                                Some(text.as_str())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
+/// Extract thinking/reasoning blocks, which text-only filters would otherwise drop
+fn collect_thinking(content: &[ContentBlock]) -> Vec<String> {
+    content
+        .iter()
+        .filter_map(|b| {
+            if let ContentBlock::Thinking { thinking, .. } = b {
+                Some(thinking.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
 
-                    match judge.evaluate(task, &output, criteria).await {
-                        Ok((passed, reason)) => {
-                            if passed {
-                                EvalResult::Pass
-                            } else {
-                                EvalResult::Fail(reason)
-                            }
-                        }
-                        Err(e) => EvalResult::Fail(format!("Judge error: {}", e)),
-                    }
-                }
-                Err(e) => EvalResult::Fail(format!("LLM request failed: {}", e)),
+/// Render each tool call as `name(input)` on its own line for the judge prompt
+fn summarize_tool_calls(content: &[ContentBlock]) -> String {
+    let calls: Vec<String> = content
+        .iter()
+        .filter_map(|b| {
+            if let ContentBlock::ToolUse { name, input, .. } = b {
+                Some(format!("- {}({})", name, input))
+            } else {
+                None
             }
-        }
+        })
+        .collect();
+
+    if calls.is_empty() {
+        "(no tool calls)".to_string()
+    } else {
+        calls.join("\n")
     }
 }
 
@@ -1006,21 +4489,337 @@ async fn run_agent_eval(eval: &Eval, judge: Option<&Judge>) -> EvalResult {
 // Subagent Evals
 // ============================================================================
 
-async fn run_subagent_eval(_eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
-    // Subagent evals require mux-ffi integration with full agent spawning
-    // Skip for now - these test the agent orchestration layer
-    EvalResult::Skip("Subagent evals require mux-ffi integration".to_string())
+/// One child agent spawned through the `task` tool
+struct ChildRun {
+    /// Tools the child was given
+    tools: Vec<String>,
+    run: Result<AgentRun, String>,
+}
+
+/// The parent's `task` tool: runs a child agent loop over its own tools and returns its answer
+struct SubagentTool {
+    /// Children run on the parent's model and provider
+    model: String,
+    child_specs: Vec<serde_json::Value>,
+    system_prompt: Option<String>,
+    params: Option<serde_json::Value>,
+    children: Arc<Mutex<Vec<ChildRun>>>,
+}
+
+#[async_trait]
+impl Tool for SubagentTool {
+    fn name(&self) -> &str {
+        "task"
+    }
+    fn description(&self) -> &str {
+        "Spawns a helper subagent to carry out a task and returns its final answer"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {"prompt": {"type": "string", "description": "Task for the subagent"}},
+            "required": ["prompt"]
+        })
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let prompt = params
+            .get("prompt")
+            .and_then(|p| p.as_str())
+            .context("task needs a \"prompt\"")?;
+
+        // Each child gets a fresh registry, so nothing leaks between children or from the parent
+        let registry = Registry::new();
+        let mut names = Vec::with_capacity(self.child_specs.len());
+        for spec in &self.child_specs {
+            let tool = DeclaredTool::from_spec(spec)?;
+            names.push(tool.name.clone());
+            registry.register(tool).await;
+        }
+        let prompt = match &self.system_prompt {
+            Some(system) => format!("{}\n\n{}", system, prompt),
+            None => prompt.to_string(),
+        };
+        let tool_names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let run = run_agent_loop(
+            provider_for_model(&self.model),
+            &self.model,
+            &registry,
+            &tool_names,
+            &prompt,
+            AGENT_LOOP_MAX_ITERATIONS,
+            self.params.as_ref(),
+        )
+        .await;
+
+        let output = match &run {
+            Ok(child) => Ok(ToolResult::text(child.final_text.clone())),
+            Err(e) => Err(anyhow::anyhow!("subagent failed: {}", e)),
+        };
+        self.children.lock().unwrap().push(ChildRun {
+            tools: names,
+            run: run.map_err(|e| e.to_string()),
+        });
+        output
+    }
+}
+
+/// Tool specs from a `given` list whose entries are specs or bare tool names
+fn tool_specs(value: Option<&serde_json::Value>) -> Vec<serde_json::Value> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| match entry.as_str() {
+                    Some(name) => serde_json::json!({ "name": name }),
+                    None => entry.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn run_subagent_eval(
+    eval: &Eval,
+    opts: &RunOptions,
+    judge: Option<&JudgePanel>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let action = eval
+        .when
+        .get("action")
+        .and_then(|a| a.as_str())
+        .unwrap_or("");
+    if action != "spawn_subagent" {
+        // Hooks and resume need mux's own subagent lifecycle, which the runner doesn't drive
+        return EvalResult::Skip(format!("Subagent action '{}' not supported", action));
+    }
+    let model = agent_model(eval);
+    let provider = provider_for_model(model);
+    if let Some(reason) = provider_unavailable(provider) {
+        return EvalResult::Skip(reason);
+    }
+    // then.expect grades the parent's final answer, so it needs a judge
+    let graded = match (eval.then.get("expect").and_then(|e| e.as_str()), judge) {
+        (Some(criteria), Some(judge)) => Some((criteria, judge)),
+        (Some(_), None) => {
+            return EvalResult::Skip("Judge not available for subagent eval".to_string())
+        }
+        (None, _) => None,
+    };
+    let params = match request_params(eval, provider, opts.strict_params) {
+        Ok(p) => p,
+        Err(e) => return EvalResult::Fail(format!("Invalid request params: {}", e)),
+    };
+
+    // The parent keeps its own tools; the child gets `child_tools`, or the parent's tools
+    // narrowed to `child_allowed_tools`
+    let parent_specs: Vec<serde_json::Value> = tool_specs(eval.given.get("parent_tools"))
+        .into_iter()
+        .filter(|spec| spec.get("name").and_then(|n| n.as_str()) != Some("task"))
+        .collect();
+    let child_specs = match eval.given.get("child_allowed_tools") {
+        Some(allowed) => {
+            let allowed: Vec<&str> = allowed
+                .as_array()
+                .map(|a| a.iter().filter_map(|n| n.as_str()).collect())
+                .unwrap_or_default();
+            parent_specs
+                .iter()
+                .filter(|spec| {
+                    let name = spec.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                    allowed.contains(&name)
+                })
+                .cloned()
+                .collect()
+        }
+        None => tool_specs(eval.given.get("child_tools")),
+    };
+
+    let children = Arc::new(Mutex::new(Vec::new()));
+    let registry = Registry::new();
+    registry
+        .register(SubagentTool {
+            model: model.to_string(),
+            child_specs,
+            system_prompt: eval
+                .when
+                .get("system_prompt")
+                .and_then(|p| p.as_str())
+                .map(str::to_string),
+            params: params.clone(),
+            children: children.clone(),
+        })
+        .await;
+    let mut names = vec!["task".to_string()];
+    for spec in &parent_specs {
+        match DeclaredTool::from_spec(spec) {
+            Ok(tool) => {
+                names.push(tool.name.clone());
+                registry.register(tool).await;
+            }
+            Err(e) => return EvalResult::Fail(format!("Invalid given.parent_tools entry: {}", e)),
+        }
+    }
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    let prompt = eval.when.get("prompt").and_then(|p| p.as_str()).unwrap_or(
+        "Use the task tool to ask a helper subagent for a one-sentence greeting, \
+             then tell me exactly what it said.",
+    );
+    let run = match run_agent_loop(
+        provider,
+        model,
+        &registry,
+        &names,
+        prompt,
+        AGENT_LOOP_MAX_ITERATIONS,
+        params.as_ref(),
+    )
+    .await
+    {
+        Ok(run) => run,
+        Err(e) => return EvalResult::Error(format!("Parent agent loop failed: {}", e)),
+    };
+    artifacts.tool_calls = run.tool_calls.clone();
+    artifacts.loop_errors = run.errors.clone();
+    artifacts.add_metric("iterations", run.iterations as f64);
+
+    let children = std::mem::take(&mut *children.lock().unwrap());
+    let mut usage = run.usage;
+    let mut child_tools: Vec<String> = Vec::new();
+    let mut finished = 0;
+    for child in &children {
+        child_tools.extend(child.tools.iter().cloned());
+        if let Ok(child_run) = &child.run {
+            usage += child_run.usage;
+            finished += 1;
+        }
+    }
+    artifacts.add_metric("subagents", children.len() as f64);
+
+    let result = check_subagents(eval, &children, finished, &child_tools);
+    let result = match (result, graded) {
+        // The parent's final answer must actually use what the child reported
+        (EvalResult::Pass, Some((criteria, judge))) => {
+            let trace = format_tool_trace(&run.tool_calls);
+            judge_output(
+                judge,
+                prompt,
+                &run.final_text,
+                criteria,
+                Some(&trace),
+                artifacts,
+            )
+            .await
+        }
+        (other, _) => other,
+    };
+    enforce_token_budget(eval, result, usage, artifacts)
+}
+
+/// Structural checks on spawned children: `then.child_created`, `then.child_tools` and
+/// `then.child_tools_not_contain`
+fn check_subagents(
+    eval: &Eval,
+    children: &[ChildRun],
+    finished: usize,
+    child_tools: &[String],
+) -> EvalResult {
+    if children.is_empty() {
+        return EvalResult::Fail("Parent never spawned a subagent".to_string());
+    }
+    let created = eval.then.get("child_created").and_then(|c| c.as_bool());
+    if created == Some(true) && finished == 0 {
+        let errors: Vec<&str> = children
+            .iter()
+            .filter_map(|c| c.run.as_ref().err().map(String::as_str))
+            .collect();
+        return EvalResult::Fail(format!("No subagent completed: {}", errors.join("; ")));
+    }
+
+    let names = |key: &str| -> Vec<String> {
+        eval.then
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|n| n.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let missing: Vec<String> = names("child_tools")
+        .into_iter()
+        .filter(|t| !child_tools.contains(t))
+        .collect();
+    if !missing.is_empty() {
+        return EvalResult::Fail(format!(
+            "Subagent was missing tools {:?}, had {:?}",
+            missing, child_tools
+        ));
+    }
+    let leaked: Vec<String> = names("child_tools_not_contain")
+        .into_iter()
+        .filter(|t| child_tools.contains(t))
+        .collect();
+    if !leaked.is_empty() {
+        return EvalResult::Fail(format!(
+            "Subagent was given filtered-out tools {:?}",
+            leaked
+        ));
+    }
+
+    EvalResult::Pass
 }
 
 // ============================================================================
 // Transcript Evals
 // ============================================================================
 
-async fn run_transcript_eval(eval: &Eval) -> EvalResult {
+/// Store backing a transcript eval: `given.store` ("memory" or "file"), else `default`
+///
+/// A file store lives in a fresh temp dir (under `given.dir` when set) that is deleted when
+/// the returned guard drops, so nothing is left on disk whether the eval passes or fails.
+fn transcript_store(
+    eval: &Eval,
+    default: TranscriptBackend,
+) -> Result<(Box<dyn TranscriptStore>, Option<tempfile::TempDir>)> {
+    let backend = match eval.given.get("store").and_then(|s| s.as_str()) {
+        Some(name) => TranscriptBackend::from_str(name, true)
+            .map_err(|_| anyhow::anyhow!("Unknown transcript store: {}", name))?,
+        None => default,
+    };
+
+    match backend {
+        TranscriptBackend::Memory => Ok((Box::new(MemoryTranscriptStore::new()), None)),
+        TranscriptBackend::File => {
+            let mut builder = tempfile::Builder::new();
+            builder.prefix("mux-evals-transcript-");
+            let dir = match eval.given.get("dir").and_then(|d| d.as_str()) {
+                Some(parent) => {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent))?;
+                    builder.tempdir_in(parent)
+                }
+                None => builder.tempdir(),
+            }
+            .context("Failed to create transcript temp dir")?;
+            Ok((Box::new(FileTranscriptStore::new(dir.path())), Some(dir)))
+        }
+    }
+}
+
+async fn run_transcript_eval(eval: &Eval, opts: &RunOptions) -> EvalResult {
+    // Every case runs against the selected backend; `dir` keeps a file store's temp dir alive
+    let (store, dir) = match transcript_store(eval, opts.transcript_store) {
+        Ok(s) => s,
+        Err(e) => return EvalResult::Error(format!("{:#}", e)),
+    };
+
     match eval.id.as_str() {
         "transcript-001" => {
             // transcript_save - Can save a conversation
-            let store = MemoryTranscriptStore::new();
             let messages = vec![
                 Message {
                     role: Role::User,
@@ -1042,7 +4841,6 @@ async fn run_transcript_eval(eval: &Eval) -> EvalResult {
         }
         "transcript-002" => {
             // transcript_load - Saved transcript can be loaded
-            let store = MemoryTranscriptStore::new();
             let messages = vec![
                 Message {
                     role: Role::User,
@@ -1075,7 +4873,6 @@ async fn run_transcript_eval(eval: &Eval) -> EvalResult {
         }
         "transcript-003" => {
             // transcript_missing - Loading missing transcript returns None
-            let store = MemoryTranscriptStore::new();
             match store.load("nonexistent").await {
                 Ok(None) => EvalResult::Pass,
                 Ok(Some(_)) => EvalResult::Fail("Expected None for missing transcript".to_string()),
@@ -1084,7 +4881,6 @@ async fn run_transcript_eval(eval: &Eval) -> EvalResult {
         }
         "transcript-004" => {
             // transcript_preserves_tool_use - Tool use messages preserved
-            let store = MemoryTranscriptStore::new();
             let messages = vec![Message {
                 role: Role::Assistant,
                 content: vec![ContentBlock::ToolUse {
@@ -1115,7 +4911,6 @@ async fn run_transcript_eval(eval: &Eval) -> EvalResult {
         }
         "transcript-005" => {
             // transcript_overwrite - Saving overwrites existing
-            let store = MemoryTranscriptStore::new();
 
             // Save initial
             let messages1 = vec![Message {
@@ -1128,64 +4923,477 @@ async fn run_transcript_eval(eval: &Eval) -> EvalResult {
                 return EvalResult::Fail(format!("First save failed: {}", e));
             }
 
-            // Overwrite
-            let messages2 = vec![Message {
-                role: Role::User,
-                content: vec![ContentBlock::Text {
-                    text: "Second".into(),
-                }],
-            }];
-            if let Err(e) = store.save("test-agent", &messages2).await {
-                return EvalResult::Fail(format!("Second save failed: {}", e));
+            // Overwrite
+            let messages2 = vec![Message {
+                role: Role::User,
+                content: vec![ContentBlock::Text {
+                    text: "Second".into(),
+                }],
+            }];
+            if let Err(e) = store.save("test-agent", &messages2).await {
+                return EvalResult::Fail(format!("Second save failed: {}", e));
+            }
+
+            match store.load("test-agent").await {
+                Ok(Some(loaded)) => {
+                    if let ContentBlock::Text { text } = &loaded[0].content[0] {
+                        if text == "Second" {
+                            EvalResult::Pass
+                        } else {
+                            EvalResult::Fail(format!("Expected 'Second', got '{}'", text))
+                        }
+                    } else {
+                        EvalResult::Fail("Wrong content type".to_string())
+                    }
+                }
+                Ok(None) => EvalResult::Fail("Transcript not found".to_string()),
+                Err(e) => EvalResult::Fail(format!("Load failed: {}", e)),
+            }
+        }
+        "transcript-006" => {
+            // transcript_roundtrip_idempotent - Repeated save/load cycles don't drift
+            let cycles = eval
+                .given
+                .get("cycles")
+                .and_then(|c| c.as_u64())
+                .unwrap_or(3);
+
+            let mut messages = vec![
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::Text {
+                        text: "Summarize the report".into(),
+                    }],
+                },
+                Message {
+                    role: Role::Assistant,
+                    content: vec![ContentBlock::ToolUse {
+                        id: "tool-1".into(),
+                        name: "fetch".into(),
+                        input: serde_json::json!({
+                            "zeta": [1, 2.5, -0.0, 1e21],
+                            "alpha": {"nested": true, "unicode": "héllo \u{1F600}"},
+                            "empty": {}
+                        }),
+                    }],
+                },
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::ToolResult {
+                        tool_use_id: "tool-1".into(),
+                        content: "{\"pages\": 3}".into(),
+                        is_error: false,
+                    }],
+                },
+            ];
+            let original = match serde_json::to_string(&messages) {
+                Ok(s) => s,
+                Err(e) => return EvalResult::Fail(format!("Serialize failed: {}", e)),
+            };
+
+            for cycle in 1..=cycles {
+                if let Err(e) = store.save("test-agent", &messages).await {
+                    return EvalResult::Fail(format!("Save failed on cycle {}: {}", cycle, e));
+                }
+                messages = match store.load("test-agent").await {
+                    Ok(Some(loaded)) => loaded,
+                    Ok(None) => {
+                        return EvalResult::Fail(format!("Transcript missing on cycle {}", cycle))
+                    }
+                    Err(e) => {
+                        return EvalResult::Fail(format!("Load failed on cycle {}: {}", cycle, e))
+                    }
+                };
+
+                let current = match serde_json::to_string(&messages) {
+                    Ok(s) => s,
+                    Err(e) => return EvalResult::Fail(format!("Serialize failed: {}", e)),
+                };
+                if current != original {
+                    return EvalResult::Fail(format!(
+                        "Transcript drifted on cycle {}: {}",
+                        cycle,
+                        describe_diff(&original, &current)
+                    ));
+                }
+            }
+
+            EvalResult::Pass
+        }
+        "transcript-007" => {
+            // transcript_file_roundtrip - Tool use survives a save/load through the file store
+            let messages = vec![
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::Text {
+                        text: "List the files".into(),
+                    }],
+                },
+                Message {
+                    role: Role::Assistant,
+                    content: vec![ContentBlock::ToolUse {
+                        id: "tool-1".into(),
+                        name: "bash".into(),
+                        input: serde_json::json!({"command": "ls"}),
+                    }],
+                },
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::ToolResult {
+                        tool_use_id: "tool-1".into(),
+                        content: "README.md".into(),
+                        is_error: false,
+                    }],
+                },
+            ];
+            if let Err(e) = store.save("test-agent", &messages).await {
+                return EvalResult::Fail(format!("Save failed: {:#}", e));
+            }
+
+            // The transcript must really be on disk, not just cached in the store
+            if let Some(dir) = &dir {
+                let path = FileTranscriptStore::new(dir.path()).path("test-agent");
+                if !path.exists() {
+                    return EvalResult::Fail(format!("No transcript file at {}", path.display()));
+                }
+            }
+
+            let loaded = match store.load("test-agent").await {
+                Ok(Some(loaded)) => loaded,
+                Ok(None) => return EvalResult::Fail("Transcript not found".to_string()),
+                Err(e) => return EvalResult::Fail(format!("Load failed: {:#}", e)),
+            };
+            match loaded.get(1).and_then(|m| m.content.first()) {
+                Some(ContentBlock::ToolUse { id, name, input })
+                    if id == "tool-1" && name == "bash" && input["command"] == "ls" => {}
+                _ => {
+                    return EvalResult::Fail(format!(
+                        "Tool use not preserved: {}",
+                        serde_json::to_string(&loaded).unwrap_or_default()
+                    ))
+                }
+            }
+            match loaded.get(2).and_then(|m| m.content.first()) {
+                Some(ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    ..
+                }) if tool_use_id == "tool-1" && content == "README.md" => EvalResult::Pass,
+                _ => EvalResult::Fail(format!(
+                    "Tool result not preserved: {}",
+                    serde_json::to_string(&loaded).unwrap_or_default()
+                )),
+            }
+        }
+        _ => EvalResult::Skip(format!("Unknown transcript eval: {}", eval.id)),
+    }
+}
+
+// ============================================================================
+// MCP Evals - Run against the built-in mock server, or `given.command`, over stdio
+// ============================================================================
+
+/// Tool names the mock server should expose, taken from `given.server_tools`
+fn mock_server_tools(eval: &Eval) -> Vec<String> {
+    let names: Vec<String> = eval
+        .given
+        .get("server_tools")
+        .and_then(|t| t.as_array())
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|t| {
+                    t.as_str()
+                        .or_else(|| t.get("name").and_then(|n| n.as_str()))
+                        .map(String::from)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if names.is_empty() {
+        vec!["echo".to_string(), "fail".to_string()]
+    } else {
+        names
+    }
+}
+
+/// Spawn this runner as a mock MCP server and connect mux's client to it
+async fn connect_mock_server(tools: &[String]) -> Result<McpClient> {
+    let exe = std::env::current_exe().context("Failed to locate runner executable")?;
+    let config = McpServerConfig {
+        name: "mock".to_string(),
+        transport: McpTransport::Stdio {
+            command: exe.display().to_string(),
+            args: vec![
+                "mock-mcp-server".to_string(),
+                "--tools".to_string(),
+                tools.join(","),
+            ],
+            env: Default::default(),
+        },
+    };
+    connect_server(config).await
+}
+
+/// Stdio config for the server in `given.command`, with `given.args` and `given.env`
+///
+/// A command that isn't installed is a skip, not a failure, so suites stay portable.
+fn external_server_config(eval: &Eval, command: &str) -> Result<McpServerConfig, EvalResult> {
+    if !command_available(command) {
+        return Err(EvalResult::Skip(format!(
+            "MCP server command '{}' not found on PATH",
+            command
+        )));
+    }
+    let strings = |key: &str| -> Vec<String> {
+        eval.given
+            .get(key)
+            .and_then(|a| a.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let env = eval
+        .given
+        .get("env")
+        .and_then(|e| e.as_object())
+        .map(|vars| {
+            vars.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(McpServerConfig {
+        name: command.to_string(),
+        transport: McpTransport::Stdio {
+            command: command.to_string(),
+            args: strings("args"),
+            env,
+        },
+    })
+}
+
+/// Whether `command` can be spawned: an existing file path, or a file in a PATH directory
+fn command_available(command: &str) -> bool {
+    if command.contains('/') || command.contains(std::path::MAIN_SEPARATOR) {
+        return Path::new(command).is_file();
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            let candidate = dir.join(command);
+            candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+        })
+    })
+}
+
+/// Shuts the server down when the eval is done with it, even if a timeout cancels the eval
+struct ServerGuard(Option<McpClient>);
+
+impl ServerGuard {
+    fn client(&self) -> &McpClient {
+        self.0.as_ref().expect("MCP server already shut down")
+    }
+
+    async fn shutdown(mut self) -> Result<()> {
+        match self.0.take() {
+            Some(client) => client.shutdown().await.map_err(Into::into),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ServerGuard {
+    fn drop(&mut self) {
+        // Only reached when the eval future was dropped mid-action, e.g. by --timeout
+        if let Some(client) = self.0.take() {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(async move {
+                    let _ = client.shutdown().await;
+                });
+            }
+        }
+    }
+}
+
+/// Connect mux's client to a stdio server and run the MCP handshake
+async fn connect_server(config: McpServerConfig) -> Result<McpClient> {
+    let client = McpClient::connect(config).await?;
+    // Don't leave the server process behind when the handshake fails
+    if let Err(e) = client.initialize().await {
+        let _ = client.shutdown().await;
+        return Err(e.into());
+    }
+    Ok(client)
+}
+
+async fn run_mcp_eval(eval: &Eval) -> EvalResult {
+    let action = eval
+        .when
+        .get("action")
+        .and_then(|a| a.as_str())
+        .unwrap_or("");
+    if action == "run_agent" {
+        return EvalResult::Skip("Requires agent execution".to_string());
+    }
+
+    // An external server's tools are unknown, so only the ones the eval names are expected
+    let command = eval.given.get("command").and_then(|c| c.as_str());
+    let mut server_tools = match command {
+        Some(_) => eval
+            .given
+            .get("server_tools")
+            .map(|_| mock_server_tools(eval))
+            .unwrap_or_default(),
+        None => mock_server_tools(eval),
+    };
+    if let Some(names) = eval.then.get("tools_include").and_then(|t| t.as_array()) {
+        server_tools.extend(names.iter().filter_map(|n| n.as_str().map(String::from)));
+    }
+
+    let connected = match command {
+        Some(command) => match external_server_config(eval, command) {
+            Ok(config) => connect_server(config)
+                .await
+                .with_context(|| format!("Failed to start MCP server '{}'", command)),
+            Err(skip) => return skip,
+        },
+        None => connect_mock_server(&server_tools)
+            .await
+            .context("Failed to start mock MCP server"),
+    };
+    let server = match connected {
+        Ok(c) => ServerGuard(Some(c)),
+        Err(e) => return EvalResult::Error(format!("{:#}", e)),
+    };
+    let client = server.client();
+
+    let result = match action {
+        "mcp_list_tools" => match client.list_tools().await {
+            Ok(tools) => {
+                let expected = eval.then.get("tools_count").and_then(|c| c.as_u64());
+                let listed: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+                let missing: Vec<&str> = server_tools
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|name| !listed.contains(name))
+                    .collect();
+                match expected {
+                    Some(n) if tools.len() as u64 != n => {
+                        EvalResult::Fail(format!("Expected {} tools, got {}", n, tools.len()))
+                    }
+                    _ if !missing.is_empty() => EvalResult::Fail(format!(
+                        "Server tools missing from discovery: {} (listed: {})",
+                        missing.join(", "),
+                        listed.join(", ")
+                    )),
+                    _ => EvalResult::Pass,
+                }
             }
+            Err(e) => EvalResult::Fail(format!("list_tools failed: {}", e)),
+        },
+        "mcp_call_tool" => {
+            let tool = eval.when.get("tool").and_then(|t| t.as_str()).unwrap_or("");
+            let input = eval
+                .when
+                .get("input")
+                .cloned()
+                .unwrap_or(serde_json::json!({}));
+            let expect_error = eval
+                .then
+                .get("is_error")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false);
 
-            match store.load("test-agent").await {
-                Ok(Some(loaded)) => {
-                    if let ContentBlock::Text { text } = &loaded[0].content[0] {
-                        if text == "Second" {
-                            EvalResult::Pass
-                        } else {
-                            EvalResult::Fail(format!("Expected 'Second', got '{}'", text))
-                        }
+            match client.call_tool(tool, input).await {
+                Ok(r) if expect_error => {
+                    if r.is_error {
+                        EvalResult::Pass
                     } else {
-                        EvalResult::Fail("Wrong content type".to_string())
+                        EvalResult::Fail(format!("Expected '{}' to report an error", tool))
                     }
                 }
-                Ok(None) => EvalResult::Fail("Transcript not found".to_string()),
-                Err(e) => EvalResult::Fail(format!("Load failed: {}", e)),
+                Ok(r) if r.is_error => {
+                    EvalResult::Fail(format!("Tool '{}' reported an error", tool))
+                }
+                Ok(r) => {
+                    let content = serde_json::to_string(&r.content).unwrap_or_default();
+                    match eval.then.get("result_contains").and_then(|c| c.as_str()) {
+                        Some(needle) if !content.contains(needle) => EvalResult::Fail(format!(
+                            "Expected '{}' in result, got: {}",
+                            needle, content
+                        )),
+                        _ => EvalResult::Pass,
+                    }
+                }
+                // A protocol-level error also counts as the error propagating
+                Err(_) if expect_error => EvalResult::Pass,
+                Err(e) => EvalResult::Fail(format!("call_tool failed: {}", e)),
             }
         }
-        _ => EvalResult::Skip(format!("Unknown transcript eval: {}", eval.id)),
-    }
-}
-
-// ============================================================================
-// MCP Evals (requires real MCP server, skip for now)
-// ============================================================================
+        "mcp_disconnect" => {
+            return match server.shutdown().await {
+                Ok(_) => EvalResult::Pass,
+                Err(e) => EvalResult::Fail(format!("Shutdown failed: {}", e)),
+            };
+        }
+        other => EvalResult::Skip(format!("Unknown MCP action: {}", other)),
+    };
 
-async fn run_mcp_eval(_eval: &Eval) -> EvalResult {
-    EvalResult::Skip("MCP evals require running MCP server".to_string())
+    let _ = server.shutdown().await;
+    result
 }
 
 // ============================================================================
 // LLM Provider Evals - Test different LLM providers
 // ============================================================================
 
-async fn run_llm_eval(eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
+async fn run_llm_eval(
+    eval: &Eval,
+    opts: &RunOptions,
+    judge: Option<&JudgePanel>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
     // Determine which provider to test
     let provider = eval.provider.as_deref().unwrap_or("anthropic");
 
+    if eval
+        .then
+        .get("deterministic")
+        .and_then(|d| d.as_bool())
+        .unwrap_or(false)
+    {
+        if !opts.deterministic {
+            return EvalResult::Skip("then.deterministic requires --deterministic".to_string());
+        }
+        return run_determinism_probe(eval, provider).await;
+    }
+
+    let params = match request_params(eval, provider, opts.strict_params) {
+        Ok(p) => p,
+        Err(e) => return EvalResult::Fail(format!("Invalid request params: {}", e)),
+    };
+
+    if let Some(blocks) = eval.when.get("content_blocks") {
+        return run_multi_block_eval(eval, provider, blocks, params.as_ref(), judge, artifacts)
+            .await;
+    }
+
     match provider {
         "anthropic" => {
-            if std::env::var("ANTHROPIC_API_KEY").is_err() {
-                return EvalResult::Skip("ANTHROPIC_API_KEY not set".to_string());
+            if let Some(reason) = provider_unavailable("anthropic") {
+                return EvalResult::Skip(reason);
             }
 
             match eval.id.as_str() {
                 "llm-001" => {
                     // llm_anthropic_basic - Basic Anthropic call
-                    let client = AnthropicClient::from_env().unwrap();
-                    let request = Request {
+                    let client = llm_client_for("anthropic").unwrap();
+                    let mut request = Request {
                         model: "claude-sonnet-4-20250514".to_string(),
                         messages: vec![Message {
                             role: Role::User,
@@ -1196,24 +5404,31 @@ async fn run_llm_eval(eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
                         max_tokens: Some(50),
                         ..Default::default()
                     };
+                    apply_request_params(&mut request, params.as_ref());
 
-                    match client.create_message(&request).await {
+                    match send_request(&client, "anthropic", &request, false).await {
                         Ok(response) => {
-                            if !response.content.is_empty() {
-                                EvalResult::Pass
-                            } else {
+                            let result = if response.content.is_empty() {
                                 EvalResult::Fail("Empty response from Anthropic".to_string())
-                            }
+                            } else {
+                                check_golden_output(eval, &collect_text(&response.content))
+                                    .unwrap_or(EvalResult::Pass)
+                            };
+                            let usage = TokenUsage::of(&response);
+                            let result = enforce_token_budget(eval, result, usage, artifacts);
+                            enforce_usage_limits(eval, result, &request.model, usage, artifacts)
                         }
-                        Err(e) => EvalResult::Fail(format!("Anthropic API error: {}", e)),
+                        Err(e) => EvalResult::Error(format!("Anthropic API error: {}", e)),
                     }
                 }
                 "llm-002" => {
                     // llm_anthropic_streaming - Streaming response
-                    use futures::StreamExt;
+                    if offline_llm() {
+                        return EvalResult::Skip("Streaming can't run offline".to_string());
+                    }
 
                     let client = AnthropicClient::from_env().unwrap();
-                    let request = Request {
+                    let mut request = Request {
                         model: "claude-sonnet-4-20250514".to_string(),
                         messages: vec![Message {
                             role: Role::User,
@@ -1222,40 +5437,80 @@ async fn run_llm_eval(eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
                             }],
                         }],
                         max_tokens: Some(100),
+                        // Streams bypass send_request, so resolve its temperature here
+                        temperature: Some(sampling_temperature(false)),
                         ..Default::default()
                     };
+                    apply_request_params(&mut request, params.as_ref());
 
+                    record_provider_request("anthropic", false);
                     let mut stream = client.create_message_stream(&request);
-                    let mut got_event = false;
+                    let mut events = 0;
+                    let mut deltas = 0;
+                    let mut text = String::new();
+                    let mut stopped = false;
+                    // Stands in for send_request's response in the trace and usage totals
+                    let mut streamed = Response::default();
 
+                    let started = Instant::now();
                     while let Some(event) = stream.next().await {
+                        events += 1;
                         match event {
-                            Ok(_) => got_event = true,
-                            Err(e) => return EvalResult::Fail(format!("Stream error: {}", e)),
+                            Ok(StreamEvent::ContentBlockDelta { text: delta, .. }) => {
+                                deltas += 1;
+                                text.push_str(&delta);
+                            }
+                            Ok(StreamEvent::MessageDelta { usage, .. }) => {
+                                // Reported totals are cumulative, so keep the largest
+                                streamed.usage.input_tokens =
+                                    streamed.usage.input_tokens.max(usage.input_tokens);
+                                streamed.usage.output_tokens =
+                                    streamed.usage.output_tokens.max(usage.output_tokens);
+                            }
+                            Ok(StreamEvent::MessageStop) => stopped = true,
+                            Ok(_) => {}
+                            Err(e) => {
+                                trace::record("anthropic", false, &request, Err(e.to_string()));
+                                return EvalResult::Error(format!("Stream error: {}", e));
+                            }
                         }
                     }
+                    record_call_timing("provider", started.elapsed());
+                    streamed.content = vec![ContentBlock::Text { text: text.clone() }];
+                    trace::record("anthropic", false, &request, Ok(&streamed));
+                    meter_usage("anthropic", &request.model, &streamed);
+                    artifacts.add_metric("stream_events", events as f64);
+                    artifacts.add_metric("text_deltas", deltas as f64);
 
-                    if got_event {
-                        EvalResult::Pass
-                    } else {
+                    // A provider can emit deltas and still never signal completion
+                    if events == 0 {
                         EvalResult::Fail("No streaming events received".to_string())
+                    } else if deltas == 0 {
+                        EvalResult::Fail("no text delta events received".to_string())
+                    } else if text.trim().is_empty() {
+                        EvalResult::Fail(format!(
+                            "{} text deltas concatenate to empty text",
+                            deltas
+                        ))
+                    } else if !stopped {
+                        EvalResult::Fail("no stop event received".to_string())
+                    } else {
+                        EvalResult::Pass
                     }
                 }
                 _ => EvalResult::Skip(format!("Unknown Anthropic eval: {}", eval.id)),
             }
         }
         "openai" => {
-            if std::env::var("OPENAI_API_KEY").is_err() {
-                return EvalResult::Skip("OPENAI_API_KEY not set".to_string());
+            if let Some(reason) = provider_unavailable("openai") {
+                return EvalResult::Skip(reason);
             }
 
             match eval.id.as_str() {
                 "llm-003" => {
                     // llm_openai_basic - Basic OpenAI call
-                    use mux::llm::OpenAIClient;
-
-                    let client = OpenAIClient::from_env().unwrap();
-                    let request = Request {
+                    let client = llm_client_for("openai").unwrap();
+                    let mut request = Request {
                         model: "gpt-4o-mini".to_string(),
                         messages: vec![Message {
                             role: Role::User,
@@ -1266,33 +5521,36 @@ async fn run_llm_eval(eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
                         max_tokens: Some(50),
                         ..Default::default()
                     };
+                    apply_request_params(&mut request, params.as_ref());
 
-                    match client.create_message(&request).await {
+                    match send_request(&client, "openai", &request, false).await {
                         Ok(response) => {
-                            if !response.content.is_empty() {
-                                EvalResult::Pass
-                            } else {
+                            let result = if response.content.is_empty() {
                                 EvalResult::Fail("Empty response from OpenAI".to_string())
-                            }
+                            } else {
+                                check_golden_output(eval, &collect_text(&response.content))
+                                    .unwrap_or(EvalResult::Pass)
+                            };
+                            let usage = TokenUsage::of(&response);
+                            let result = enforce_token_budget(eval, result, usage, artifacts);
+                            enforce_usage_limits(eval, result, &request.model, usage, artifacts)
                         }
-                        Err(e) => EvalResult::Fail(format!("OpenAI API error: {}", e)),
+                        Err(e) => EvalResult::Error(format!("OpenAI API error: {}", e)),
                     }
                 }
                 _ => EvalResult::Skip(format!("Unknown OpenAI eval: {}", eval.id)),
             }
         }
         "gemini" => {
-            if std::env::var("GEMINI_API_KEY").is_err() {
-                return EvalResult::Skip("GEMINI_API_KEY not set".to_string());
+            if let Some(reason) = provider_unavailable("gemini") {
+                return EvalResult::Skip(reason);
             }
 
             match eval.id.as_str() {
                 "llm-005" => {
                     // llm_gemini_basic - Basic Gemini call
-                    use mux::llm::GeminiClient;
-
-                    let client = GeminiClient::from_env().unwrap();
-                    let request = Request {
+                    let client = llm_client_for("gemini").unwrap();
+                    let mut request = Request {
                         model: "gemini-2.0-flash".to_string(),
                         messages: vec![Message {
                             role: Role::User,
@@ -1303,21 +5561,639 @@ async fn run_llm_eval(eval: &Eval, _judge: Option<&Judge>) -> EvalResult {
                         max_tokens: Some(50),
                         ..Default::default()
                     };
+                    apply_request_params(&mut request, params.as_ref());
 
-                    match client.create_message(&request).await {
+                    match send_request(&client, "gemini", &request, false).await {
                         Ok(response) => {
-                            if !response.content.is_empty() {
-                                EvalResult::Pass
-                            } else {
+                            let result = if response.content.is_empty() {
                                 EvalResult::Fail("Empty response from Gemini".to_string())
-                            }
+                            } else {
+                                check_golden_output(eval, &collect_text(&response.content))
+                                    .unwrap_or(EvalResult::Pass)
+                            };
+                            let usage = TokenUsage::of(&response);
+                            let result = enforce_token_budget(eval, result, usage, artifacts);
+                            enforce_usage_limits(eval, result, &request.model, usage, artifacts)
                         }
-                        Err(e) => EvalResult::Fail(format!("Gemini API error: {}", e)),
+                        Err(e) => EvalResult::Error(format!("Gemini API error: {}", e)),
                     }
                 }
                 _ => EvalResult::Skip(format!("Unknown Gemini eval: {}", eval.id)),
             }
         }
+        "ollama" => {
+            // llm-001's basic call against a locally hosted model
+            if !offline_llm() {
+                let local = OllamaClient::from_env();
+                if !local.reachable().await {
+                    return EvalResult::Skip(format!("Ollama not reachable at {}", local.host()));
+                }
+            }
+            let client = match llm_client_for("ollama") {
+                Ok(c) => c,
+                Err(e) => return EvalResult::Error(format!("Ollama client: {}", e)),
+            };
+            let model = eval
+                .given
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or(OLLAMA_DEFAULT_MODEL);
+            let prompt = eval
+                .when
+                .get("prompt")
+                .and_then(|p| p.as_str())
+                .unwrap_or("Say 'hello' and nothing else.");
+            let mut request = Request {
+                model: model.to_string(),
+                messages: vec![Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::Text {
+                        text: prompt.to_string(),
+                    }],
+                }],
+                max_tokens: Some(50),
+                ..Default::default()
+            };
+            apply_request_params(&mut request, params.as_ref());
+
+            match send_request(&client, "ollama", &request, false).await {
+                Ok(response) => {
+                    let result = if response.content.is_empty() {
+                        EvalResult::Fail("Empty response from Ollama".to_string())
+                    } else {
+                        check_golden_output(eval, &collect_text(&response.content))
+                            .unwrap_or(EvalResult::Pass)
+                    };
+                    let usage = TokenUsage::of(&response);
+                    let result = enforce_token_budget(eval, result, usage, artifacts);
+                    enforce_usage_limits(eval, result, &request.model, usage, artifacts)
+                }
+                Err(e) => EvalResult::Error(format!("Ollama API error: {}", e)),
+            }
+        }
         _ => EvalResult::Skip(format!("Unknown LLM provider: {}", provider)),
     }
 }
+
+/// Compare the trimmed output with `then.equals` or the `then.matches` regex, without a judge
+///
+/// Both ignore case unless `then.case_sensitive` is true. Returns None when the eval sets
+/// neither, so callers fall back to their own checks.
+fn check_golden_output(eval: &Eval, output: &str) -> Option<EvalResult> {
+    let case_sensitive = eval
+        .then
+        .get("case_sensitive")
+        .and_then(|c| c.as_bool())
+        .unwrap_or(false);
+    let output = output.trim();
+
+    if let Some(expected) = eval.then.get("equals").and_then(|e| e.as_str()) {
+        let expected = expected.trim();
+        let equal = if case_sensitive {
+            output == expected
+        } else {
+            output.to_lowercase() == expected.to_lowercase()
+        };
+        if !equal {
+            return Some(EvalResult::Fail(format!(
+                "Expected output '{}', got: {}",
+                expected, output
+            )));
+        }
+    }
+
+    if let Some(pattern) = eval.then.get("matches").and_then(|m| m.as_str()) {
+        let regex = match regex::RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Some(EvalResult::Fail(format!(
+                    "Invalid then.matches regex: {}",
+                    e
+                )))
+            }
+        };
+        if !regex.is_match(output) {
+            return Some(EvalResult::Fail(format!(
+                "Output doesn't match /{}/: {}",
+                pattern, output
+            )));
+        }
+    }
+
+    let checked = eval.then.get("equals").is_some() || eval.then.get("matches").is_some();
+    checked.then_some(EvalResult::Pass)
+}
+
+/// Model for `provider: "ollama"` evals that don't name one in `given.model`
+const OLLAMA_DEFAULT_MODEL: &str = "llama3.2";
+
+/// Request fields that `when.params` and `when.provider_overrides` can set
+const SUPPORTED_REQUEST_PARAMS: &[&str] = &["max_tokens", "temperature"];
+
+/// Merge `when.params` with the overrides for `provider`, dropping unsupported fields
+///
+/// Provider overrides win over shared params. With `strict`, an unsupported field is an
+/// error instead of a warning.
+fn request_params(eval: &Eval, provider: &str, strict: bool) -> Result<Option<serde_json::Value>> {
+    let mut merged = serde_json::Map::new();
+    if let Some(params) = eval.when.get("params").and_then(|p| p.as_object()) {
+        merged.extend(params.clone());
+    }
+    if let Some(overrides) = eval
+        .when
+        .get("provider_overrides")
+        .and_then(|o| o.get(provider))
+        .and_then(|o| o.as_object())
+    {
+        merged.extend(overrides.clone());
+    }
+
+    let unsupported: Vec<String> = merged
+        .keys()
+        .filter(|k| !SUPPORTED_REQUEST_PARAMS.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    for key in unsupported {
+        if strict {
+            anyhow::bail!("unsupported request param '{}' for {}", key, provider);
+        }
+        eprintln!(
+            "{} unsupported request param '{}' for {} ignored",
+            "warning:".yellow().bold(),
+            key,
+            provider
+        );
+        merged.remove(&key);
+    }
+
+    Ok((!merged.is_empty()).then_some(serde_json::Value::Object(merged)))
+}
+
+/// Apply params resolved by `request_params` to a request
+fn apply_request_params(request: &mut Request, params: Option<&serde_json::Value>) {
+    let params = match params.and_then(|p| p.as_object()) {
+        Some(p) => p,
+        None => return,
+    };
+
+    for (key, value) in params {
+        let applied = match key.as_str() {
+            "max_tokens" => value
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .map(|n| request.max_tokens = Some(n))
+                .is_some(),
+            "temperature" => value
+                .as_f64()
+                .map(|t| request.temperature = Some(t as f32))
+                .is_some(),
+            // Anything else was already filtered out by request_params
+            _ => true,
+        };
+        if !applied {
+            eprintln!(
+                "{} request param '{}' has invalid value {}, ignored",
+                "warning:".yellow().bold(),
+                key,
+                value
+            );
+        }
+    }
+}
+
+// ============================================================================
+// Determinism Probe - Same request twice must produce identical output
+// ============================================================================
+
+/// Offline client installed by --mock-llm; when set it serves every provider
+static MOCK_LLM: OnceLock<Arc<dyn LlmClient>> = OnceLock::new();
+
+/// Cassette directory and mode from --cassette/--cassette-mode
+static CASSETTE: OnceLock<(PathBuf, CassetteMode)> = OnceLock::new();
+
+fn llm_client_for(provider: &str) -> Result<Arc<dyn LlmClient>> {
+    if let Some(mock) = MOCK_LLM.get() {
+        return Ok(mock.clone());
+    }
+    if let Some((dir, CassetteMode::Replay)) = CASSETTE.get() {
+        return Ok(Arc::new(CassetteClient::replay(dir, provider)));
+    }
+    let client: Arc<dyn LlmClient> = match provider {
+        "anthropic" => Arc::new(AnthropicClient::from_env()?),
+        "openai" => Arc::new(OpenAIClient::from_env()?),
+        "gemini" => Arc::new(mux::llm::GeminiClient::from_env()?),
+        "ollama" => Arc::new(OllamaClient::from_env()),
+        other => anyhow::bail!("Unknown LLM provider: {}", other),
+    };
+    match CASSETTE.get() {
+        Some((dir, _)) => Ok(Arc::new(CassetteClient::record(dir, provider, client))),
+        None => Ok(client),
+    }
+}
+
+/// Whether provider calls are answered locally (--mock-llm or --cassette-mode replay)
+fn offline_llm() -> bool {
+    MOCK_LLM.get().is_some() || matches!(CASSETTE.get(), Some((_, CassetteMode::Replay)))
+}
+
+fn collect_text(content: &[ContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|b| {
+            if let ContentBlock::Text { text } = b {
+                Some(text.as_str())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Skip reason when the provider is unknown or its API key isn't set
+fn provider_unavailable(provider: &str) -> Option<String> {
+    let key = match provider {
+        "anthropic" => "ANTHROPIC_API_KEY",
+        "openai" => "OPENAI_API_KEY",
+        "gemini" => "GEMINI_API_KEY",
+        // Local models need no key; the eval checks the server is up instead
+        "ollama" => return None,
+        _ => return Some(format!("Unknown LLM provider: {}", provider)),
+    };
+    if offline_llm() {
+        return None;
+    }
+    std::env::var(key)
+        .is_err()
+        .then(|| format!("{} not set", key))
+}
+
+async fn run_determinism_probe(eval: &Eval, provider: &str) -> EvalResult {
+    if let Some(reason) = provider_unavailable(provider) {
+        return EvalResult::Skip(reason);
+    }
+
+    let client = match llm_client_for(provider) {
+        Ok(c) => c,
+        Err(e) => return EvalResult::Error(format!("Failed to create client: {}", e)),
+    };
+
+    let model = eval
+        .given
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or("claude-sonnet-4-20250514");
+    let prompt = eval
+        .when
+        .get("prompt")
+        .and_then(|p| p.as_str())
+        .unwrap_or("Say hello");
+
+    let request = Request {
+        model: model.to_string(),
+        messages: vec![Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text {
+                text: prompt.to_string(),
+            }],
+        }],
+        max_tokens: Some(100),
+        temperature: Some(0.0),
+        ..Default::default()
+    };
+
+    let mut outputs = Vec::with_capacity(2);
+    for attempt in 1..=2 {
+        match send_request(&client, provider, &request, false).await {
+            Ok(response) => outputs.push(collect_text(&response.content)),
+            Err(e) => return EvalResult::Error(format!("Call {} failed: {}", attempt, e)),
+        }
+    }
+
+    if outputs[0] == outputs[1] {
+        EvalResult::Pass
+    } else {
+        EvalResult::Fail(format!(
+            "Outputs differ at temperature 0: {}",
+            describe_diff(&outputs[0], &outputs[1])
+        ))
+    }
+}
+
+/// Summarize where two outputs first diverge
+fn describe_diff(first: &str, second: &str) -> String {
+    let first_lines: Vec<&str> = first.lines().collect();
+    let second_lines: Vec<&str> = second.lines().collect();
+
+    for i in 0..first_lines.len().max(second_lines.len()) {
+        let a = first_lines.get(i).copied().unwrap_or("<missing>");
+        let b = second_lines.get(i).copied().unwrap_or("<missing>");
+        if a != b {
+            return format!("line {}: {:?} vs {:?}", i + 1, a, b);
+        }
+    }
+
+    format!(
+        "whitespace only ({} vs {} bytes)",
+        first.len(),
+        second.len()
+    )
+}
+
+// ============================================================================
+// Multi-Block Messages - One user message built from `when.content_blocks`
+// ============================================================================
+
+fn parse_content_block(block: &serde_json::Value) -> Result<ContentBlock> {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => {
+            let text = block
+                .get("text")
+                .and_then(|t| t.as_str())
+                .context("text block is missing \"text\"")?;
+            Ok(ContentBlock::Text {
+                text: text.to_string(),
+            })
+        }
+        Some(other) => anyhow::bail!("Unsupported content block type: {}", other),
+        None => anyhow::bail!("Content block is missing \"type\": {}", block),
+    }
+}
+
+async fn run_multi_block_eval(
+    eval: &Eval,
+    provider: &str,
+    blocks: &serde_json::Value,
+    params: Option<&serde_json::Value>,
+    judge: Option<&JudgePanel>,
+    artifacts: &mut EvalArtifacts,
+) -> EvalResult {
+    let content = match blocks.as_array().map(|b| {
+        b.iter()
+            .map(parse_content_block)
+            .collect::<Result<Vec<_>>>()
+    }) {
+        Some(Ok(content)) if !content.is_empty() => content,
+        Some(Ok(_)) | None => {
+            return EvalResult::Fail("when.content_blocks must be a non-empty array".to_string())
+        }
+        Some(Err(e)) => return EvalResult::Fail(format!("Invalid content block: {}", e)),
+    };
+
+    if let Some(reason) = provider_unavailable(provider) {
+        return EvalResult::Skip(reason);
+    }
+    let client = match llm_client_for(provider) {
+        Ok(c) => c,
+        Err(e) => return EvalResult::Error(format!("Failed to create client: {}", e)),
+    };
+
+    let model = eval
+        .given
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or("claude-sonnet-4-20250514");
+    let mut request = Request {
+        model: model.to_string(),
+        messages: vec![Message {
+            role: Role::User,
+            content,
+        }],
+        max_tokens: Some(200),
+        ..Default::default()
+    };
+    apply_request_params(&mut request, params);
+
+    let response = match send_request(&client, provider, &request, false).await {
+        Ok(r) => r,
+        Err(e) => return EvalResult::Error(format!("LLM request failed: {}", e)),
+    };
+    let output = collect_text(&response.content);
+    if output.trim().is_empty() {
+        return EvalResult::Fail("Empty response to multi-block message".to_string());
+    }
+
+    if let Some(needle) = eval.then.get("response_contains").and_then(|n| n.as_str()) {
+        if !output.to_lowercase().contains(&needle.to_lowercase()) {
+            return EvalResult::Fail(format!(
+                "Expected '{}' in response, got: {}",
+                needle, output
+            ));
+        }
+    }
+    if let Some(mismatch @ EvalResult::Fail(_)) = check_golden_output(eval, &output) {
+        return mismatch;
+    }
+
+    let result = match (eval.then.get("expect").and_then(|e| e.as_str()), judge) {
+        (Some(criteria), Some(judge)) => {
+            judge_output(judge, &eval.description, &output, criteria, None, artifacts).await
+        }
+        _ => EvalResult::Pass,
+    };
+    let usage = TokenUsage::of(&response);
+    let result = enforce_token_budget(eval, result, usage, artifacts);
+    enforce_usage_limits(eval, result, model, usage, artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    /// An eval from just the fields a test cares about; id, name and description are filled in
+    fn eval(fields: serde_json::Value) -> Eval {
+        let mut value = json!({
+            "id": "test-001",
+            "name": "test",
+            "description": "test eval",
+            "given": {},
+            "when": {},
+            "then": {},
+        });
+        for (key, field) in fields.as_object().expect("eval fields are an object") {
+            value[key] = field.clone();
+        }
+        serde_json::from_value(value).expect("test eval deserializes")
+    }
+
+    fn options(timeout_secs: Option<u64>) -> RunOptions {
+        RunOptions {
+            verbose: false,
+            deterministic: false,
+            capture_thinking: false,
+            min_score: 0.0,
+            strict_params: false,
+            timeout_secs,
+            transcript_store: TranscriptBackend::Memory,
+        }
+    }
+
+    #[tokio::test]
+    async fn sleeping_eval_times_out() {
+        // Stands in for a tool call that never comes back
+        let stalled = async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            EvalResult::Pass
+        };
+
+        match within(Duration::from_millis(50), "50ms", stalled).await {
+            EvalResult::Fail(reason) => assert_eq!(reason, "timed out after 50ms"),
+            other => panic!("expected a timeout failure, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn prompt_eval_keeps_its_result() {
+        let quick = async { EvalResult::Fail("wrong answer".to_string()) };
+
+        match within(Duration::from_secs(5), "5s", quick).await {
+            EvalResult::Fail(reason) => assert_eq!(reason, "wrong answer"),
+            other => panic!("expected the eval's own result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shards_split_evals_without_overlap() {
+        let ids: Vec<usize> = (0..10).collect();
+        let shards: Vec<Vec<usize>> = (0..3)
+            .map(|index| Shard { index, total: 3 }.select(ids.clone()))
+            .collect();
+
+        let sizes: Vec<usize> = shards.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [4, 3, 3]);
+        let mut covered: Vec<usize> = shards.concat();
+        covered.sort();
+        assert_eq!(covered, ids, "shards overlap or miss an eval");
+    }
+
+    /// Ids `load_evals` keeps from evals tagged as given, filtered by --tag and --exclude-tag
+    fn tagged_ids(tagged: &[(&str, &[&str])], tags: &[&str], excluded: &[&str]) -> Vec<String> {
+        let mut file = tempfile::NamedTempFile::new().expect("temp evals file");
+        for (id, eval_tags) in tagged {
+            let line = json!({
+                "id": id,
+                "name": id,
+                "description": "tagged eval",
+                "category": "tools",
+                "tags": eval_tags,
+                "given": {},
+                "when": {"tool": "add"},
+                "then": {},
+            });
+            writeln!(file, "{}", line).expect("write eval");
+        }
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let evals = load_evals(
+            &[file.path().to_path_buf()],
+            &[],
+            None,
+            &strings(tags),
+            &strings(excluded),
+            false,
+        )
+        .expect("evals load");
+        evals.into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn excluded_tag_wins_over_included_tag() {
+        let kept = tagged_ids(
+            &[("both", &["smoke", "slow"]), ("smoke", &["smoke"])],
+            &["smoke"],
+            &["slow"],
+        );
+        assert_eq!(kept, ["smoke"]);
+    }
+
+    #[test]
+    fn included_tag_keeps_only_tagged_evals() {
+        let kept = tagged_ids(
+            &[
+                ("smoke", &["smoke"]),
+                ("slow", &["slow"]),
+                ("untagged", &[]),
+            ],
+            &["smoke"],
+            &[],
+        );
+        assert_eq!(kept, ["smoke"]);
+    }
+
+    #[test]
+    fn untagged_evals_survive_exclusion_only() {
+        let evals: &[(&str, &[&str])] = &[("slow", &["slow"]), ("untagged", &[])];
+        assert_eq!(tagged_ids(evals, &[], &["slow"]), ["untagged"]);
+        assert_eq!(tagged_ids(evals, &[], &[]), ["slow", "untagged"]);
+    }
+
+    fn vote(passed: bool) -> Verdict {
+        Verdict {
+            passed,
+            confidence: Some(0.9),
+            reason: if passed { "looks right" } else { "looks wrong" }.to_string(),
+            exchanges: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn tie_policy_settles_an_even_split() {
+        let split = || vec![vote(true), vote(false)];
+
+        let closed = Verdict::majority(split(), JudgeTie::FailClosed, false);
+        assert!(!closed.passed);
+        assert!(closed
+            .reason
+            .contains("1/2 judges passed (a tie counts as fail: fail-closed)"));
+
+        let open = Verdict::majority(split(), JudgeTie::FailOpen, false);
+        assert!(open.passed);
+        assert!(open.reason.contains("a tie counts as pass: fail-open"));
+
+        // A tie escalation couldn't break fails closed
+        assert!(!Verdict::majority(split(), JudgeTie::Escalate, false).passed);
+    }
+
+    #[test]
+    fn escalated_vote_decides_a_tie() {
+        let verdicts = vec![vote(true), vote(false), vote(true)];
+        let verdict = Verdict::majority(verdicts, JudgeTie::Escalate, true);
+        assert!(verdict.passed);
+        assert!(verdict
+            .reason
+            .contains("2/3 judges passed (a tie, escalated to a deciding vote)"));
+        assert!(verdict.reason.contains("[fail] judge: looks wrong"));
+    }
+
+    #[test]
+    fn tie_policy_ignores_a_clear_majority() {
+        let verdicts = vec![vote(false), vote(false), vote(true)];
+        let verdict = Verdict::majority(verdicts, JudgeTie::FailOpen, false);
+        assert!(!verdict.passed);
+        assert!(!verdict.reason.contains("tie"));
+    }
+
+    #[tokio::test]
+    async fn slow_tool_eval_times_out() {
+        // The fixture answers after 3s, well past --timeout 1 and short of fetch's own limit
+        let slow = eval(json!({
+            "category": "tools",
+            "given": {"tools": ["fetch"], "http_routes": {"/slow": {"delay_ms": 3000}}},
+            "when": {"action": "execute_tool", "tool": "fetch", "input": {"url": "{fixture}/slow"}},
+            "then": {"success": true},
+        }));
+        let mut artifacts = EvalArtifacts::default();
+
+        let result = run_eval(&slow, &options(Some(1)), None, &mut artifacts).await;
+
+        match result {
+            EvalResult::Fail(reason) => assert_eq!(reason, "timed out after 1s"),
+            other => panic!("expected a timeout failure, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// ABOUTME: Offline LlmClient that answers from a fixtures file instead of calling a provider.
+// ABOUTME: Selected with --mock-llm so agent, llm and judge calls run without API keys.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use mux::llm::{ContentBlock, LlmClient, LlmError, Request, Response, Role};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Reply sent when no fixture matches, so the eval fails on its own checks instead of hanging
+const NO_MATCH_REPLY: &str = "No mock fixture matched this prompt.";
+
+/// One canned reply, used when the last user message contains `prompt_contains`
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    prompt_contains: String,
+    response: String,
+}
+
+/// Answers every request with the first fixture whose substring appears in the prompt
+pub struct MockLlmClient {
+    fixtures: Vec<Fixture>,
+}
+
+impl MockLlmClient {
+    /// Load fixtures from a JSON array of `{"prompt_contains": ..., "response": ...}`
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mock fixtures {}", path.display()))?;
+        let fixtures = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid mock fixtures in {}", path.display()))?;
+        Ok(Self { fixtures })
+    }
+
+    fn reply_for(&self, prompt: &str) -> &str {
+        match self
+            .fixtures
+            .iter()
+            .find(|f| prompt.contains(&f.prompt_contains))
+        {
+            Some(fixture) => &fixture.response,
+            None => {
+                let preview: String = prompt.chars().take(80).collect();
+                eprintln!(
+                    "{} no mock fixture matches prompt: {}",
+                    "warning:".yellow().bold(),
+                    preview
+                );
+                NO_MATCH_REPLY
+            }
+        }
+    }
+}
+
+/// Text of the last user message, including tool results fed back in the agent loop
+fn last_user_text(request: &Request) -> String {
+    let message = request
+        .messages
+        .iter()
+        .rev()
+        .find(|m| matches!(m.role, Role::User));
+    message
+        .map(|m| {
+            m.content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    ContentBlock::ToolResult { content, .. } => Some(content.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// About four characters per token, never less than one
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4).max(1)
+}
+
+#[async_trait]
+impl LlmClient for MockLlmClient {
+    async fn create_message(&self, request: &Request) -> Result<Response, LlmError> {
+        let prompt = last_user_text(request);
+        let text = self.reply_for(&prompt).to_string();
+        let output_tokens = estimate_tokens(&text);
+
+        // Report rough usage so token and cost checks behave as they would against a provider
+        let mut response = Response {
+            content: vec![ContentBlock::Text { text }],
+            ..Default::default()
+        };
+        response.usage.input_tokens = estimate_tokens(&prompt).try_into().unwrap_or_default();
+        response.usage.output_tokens = output_tokens.try_into().unwrap_or_default();
+        Ok(response)
+    }
+}
@@ -0,0 +1,113 @@
+// ABOUTME: Minimal MCP server over stdio used by the mcp evals when no external server is configured.
+// ABOUTME: Speaks just enough JSON-RPC (initialize, tools/list, tools/call) to exercise mux's MCP client.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+/// Serve the given tool names over stdin/stdout until the client closes stdin
+///
+/// Known tools have fixed behavior: `echo` returns its `text` argument and `fail`
+/// always reports an error. Any other name succeeds with a short acknowledgement.
+pub fn serve(tools: &[String]) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(v) => v,
+            Err(e) => {
+                let response = error_response(Value::Null, -32700, &format!("Parse error: {}", e));
+                writeln!(stdout, "{}", response)?;
+                stdout.flush()?;
+                continue;
+            }
+        };
+
+        // Notifications carry no id and never get a response
+        let id = match request.get("id") {
+            Some(id) => id.clone(),
+            None => continue,
+        };
+        let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let response = match method {
+            "initialize" => result_response(
+                id,
+                json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "mux-evals-mock", "version": env!("CARGO_PKG_VERSION")}
+                }),
+            ),
+            "ping" => result_response(id, json!({})),
+            "tools/list" => result_response(id, json!({ "tools": list_tools(tools) })),
+            "tools/call" => {
+                let name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                if tools.iter().any(|t| t == name) {
+                    result_response(id, call_tool(name, &arguments))
+                } else {
+                    error_response(id, -32602, &format!("Unknown tool: {}", name))
+                }
+            }
+            other => error_response(id, -32601, &format!("Method not found: {}", other)),
+        };
+
+        writeln!(stdout, "{}", response)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+fn list_tools(tools: &[String]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|name| {
+            let (description, schema) = match name.as_str() {
+                "echo" => (
+                    "Echoes input",
+                    json!({
+                        "type": "object",
+                        "properties": {"text": {"type": "string"}},
+                        "required": ["text"]
+                    }),
+                ),
+                "fail" => ("Always fails", json!({"type": "object", "properties": {}})),
+                _ => ("Mock tool", json!({"type": "object", "properties": {}})),
+            };
+            json!({"name": name, "description": description, "inputSchema": schema})
+        })
+        .collect()
+}
+
+fn call_tool(name: &str, arguments: &Value) -> Value {
+    let (text, is_error) = match name {
+        "echo" => (
+            arguments
+                .get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or("")
+                .to_string(),
+            false,
+        ),
+        "fail" => ("Tool 'fail' always fails".to_string(), true),
+        other => (format!("{} called", other), false),
+    };
+    json!({"content": [{"type": "text", "text": text}], "isError": is_error})
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
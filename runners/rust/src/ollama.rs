@@ -0,0 +1,129 @@
+// ABOUTME: Minimal LlmClient for locally hosted models served by Ollama's /api/chat endpoint.
+// ABOUTME: Text-only and non-streaming; llm evals with provider "ollama" use it via OLLAMA_HOST.
+
+use async_trait::async_trait;
+use mux::llm::{ContentBlock, LlmClient, LlmError, Request, Response, Role};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Where Ollama listens when OLLAMA_HOST is unset
+const DEFAULT_HOST: &str = "http://localhost:11434";
+
+/// How long the reachability probe waits before the server counts as down
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct OllamaClient {
+    host: String,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    #[serde(default)]
+    prompt_eval_count: u64,
+    #[serde(default)]
+    eval_count: u64,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+impl OllamaClient {
+    /// Client for OLLAMA_HOST, which like Ollama itself may omit the scheme (`127.0.0.1:11434`)
+    pub fn from_env() -> Self {
+        let host = std::env::var("OLLAMA_HOST")
+            .ok()
+            .filter(|h| !h.trim().is_empty())
+            .map(|h| {
+                let h = h.trim().trim_end_matches('/');
+                if h.contains("://") {
+                    h.to_string()
+                } else {
+                    format!("http://{}", h)
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_HOST.to_string());
+        Self {
+            host,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Whether the server answers at all, so evals can skip instead of fail when it isn't running
+    pub async fn reachable(&self) -> bool {
+        self.http
+            .get(format!("{}/api/version", self.host))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success())
+    }
+}
+
+fn api_error(status: u16, message: String) -> LlmError {
+    LlmError::Api { status, message }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn create_message(&self, request: &Request) -> Result<Response, LlmError> {
+        let messages: Vec<serde_json::Value> = request
+            .messages
+            .iter()
+            .map(|m| {
+                let role = if matches!(m.role, Role::Assistant) {
+                    "assistant"
+                } else {
+                    "user"
+                };
+                json!({"role": role, "content": crate::collect_text(&m.content)})
+            })
+            .collect();
+        let body = json!({
+            "model": request.model,
+            "messages": messages,
+            "stream": false,
+            "options": {
+                "temperature": request.temperature,
+                "num_predict": request.max_tokens,
+                // The body is ours to build, so Ollama gets the run's --seed that mux can't pass on
+                "seed": crate::run_seed(),
+            },
+        });
+
+        let reply = self
+            .http
+            .post(format!("{}/api/chat", self.host))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| api_error(0, format!("Ollama at {}: {}", self.host, e)))?;
+        let status = reply.status();
+        if !status.is_success() {
+            let text = reply.text().await.unwrap_or_default();
+            return Err(api_error(status.as_u16(), text));
+        }
+        let chat: ChatResponse = reply
+            .json()
+            .await
+            .map_err(|e| api_error(status.as_u16(), format!("Invalid Ollama reply: {}", e)))?;
+
+        let mut response = Response {
+            content: vec![ContentBlock::Text {
+                text: chat.message.content,
+            }],
+            ..Default::default()
+        };
+        response.usage.input_tokens = chat.prompt_eval_count.try_into().unwrap_or_default();
+        response.usage.output_tokens = chat.eval_count.try_into().unwrap_or_default();
+        Ok(response)
+    }
+}
@@ -0,0 +1,846 @@
+// ABOUTME: Result formatters that render finished eval runs for humans and tools.
+// ABOUTME: Each output format implements ResultFormatter; --format picks one.
+
+use crate::{
+    format_tool_trace, model_usage, provider_usage, EvalArtifacts, EvalResult, ModelUsage,
+    ProviderRequests, LOW_CONFIDENCE_PASS,
+};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Output formats selectable with --format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[value(alias = "text")]
+    Human,
+    Json,
+    Junit,
+    Markdown,
+    Csv,
+    /// Test Anything Protocol, version 13
+    Tap,
+}
+
+/// Orderings selectable with --sort-by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// Eval id, ascending
+    Id,
+    /// Wall-clock time, slowest first
+    Duration,
+    /// Failures and errors first, then skips, then passes
+    Status,
+    /// Total tokens used, most expensive first
+    Cost,
+}
+
+/// Everything reported about a single executed eval
+pub struct EvalRunResult {
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    /// Source file the eval was loaded from
+    pub file: String,
+    pub result: EvalResult,
+    pub duration: Duration,
+    pub artifacts: Option<EvalArtifacts>,
+    /// Runs it took to reach `result`; above 1 only with --retries
+    pub attempts: u32,
+}
+
+impl EvalRunResult {
+    fn status(&self) -> &'static str {
+        self.result.status()
+    }
+
+    /// The attempt that finally passed, for evals that only passed on a retry
+    fn flaky(&self) -> Option<u32> {
+        match self.result {
+            EvalResult::Pass if self.attempts > 1 => Some(self.attempts),
+            _ => None,
+        }
+    }
+
+    /// The judge's confidence, for passes it was unsure about
+    fn low_confidence(&self) -> Option<f64> {
+        match self.result {
+            EvalResult::Pass => self
+                .artifacts
+                .as_ref()
+                .and_then(|a| a.judge_confidence)
+                .filter(|c| *c < LOW_CONFIDENCE_PASS),
+            _ => None,
+        }
+    }
+
+    fn reason(&self) -> Option<&str> {
+        self.result.reason()
+    }
+}
+
+/// Stable sort, so evals that tie keep their input order
+pub fn sort_results(results: &mut [EvalRunResult], by: SortBy) {
+    match by {
+        SortBy::Id => results.sort_by(|a, b| a.id.cmp(&b.id)),
+        SortBy::Duration => results.sort_by_key(|r| std::cmp::Reverse(r.duration)),
+        SortBy::Status => results.sort_by_key(|r| match r.result {
+            EvalResult::Fail(_) | EvalResult::Error(_) => 0,
+            EvalResult::Skip(_) => 1,
+            EvalResult::Pass => 2,
+        }),
+        SortBy::Cost => results
+            .sort_by_key(|r| std::cmp::Reverse(r.artifacts.as_ref().and_then(|a| a.total_tokens))),
+    }
+}
+
+/// Renders eval results in one output format
+pub trait ResultFormatter {
+    /// Called as each eval finishes; formats that need the whole run return None
+    fn on_result(&self, _result: &EvalRunResult) -> Option<String> {
+        None
+    }
+
+    /// Render the final report once every eval has run
+    fn render(&self, results: &[EvalRunResult]) -> String;
+}
+
+pub fn build_formatter(
+    format: OutputFormat,
+    failures_only: bool,
+    verbose: bool,
+) -> Box<dyn ResultFormatter> {
+    match format {
+        OutputFormat::Human => Box::new(HumanFormatter {
+            failures_only,
+            verbose,
+        }),
+        OutputFormat::Json => Box::new(JsonFormatter),
+        OutputFormat::Junit => Box::new(JunitFormatter),
+        OutputFormat::Markdown => Box::new(MarkdownFormatter),
+        OutputFormat::Csv => Box::new(CsvFormatter),
+        OutputFormat::Tap => Box::new(TapFormatter),
+    }
+}
+
+struct Tally {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    errored: usize,
+}
+
+fn tally<'a>(results: impl IntoIterator<Item = &'a EvalRunResult>) -> Tally {
+    let mut t = Tally {
+        passed: 0,
+        failed: 0,
+        skipped: 0,
+        errored: 0,
+    };
+    for r in results {
+        match r.result {
+            EvalResult::Pass => t.passed += 1,
+            EvalResult::Fail(_) => t.failed += 1,
+            EvalResult::Skip(_) => t.skipped += 1,
+            EvalResult::Error(_) => t.errored += 1,
+        }
+    }
+    t
+}
+
+/// Categories in the order they first appear
+fn categories(results: &[EvalRunResult]) -> Vec<&str> {
+    let mut categories: Vec<&str> = Vec::new();
+    for r in results {
+        if !categories.contains(&r.category.as_str()) {
+            categories.push(&r.category);
+        }
+    }
+    categories
+}
+
+// ============================================================================
+// Human - Colored per-eval lines as they finish, summary at the end
+// ============================================================================
+
+struct HumanFormatter {
+    failures_only: bool,
+    verbose: bool,
+}
+
+impl ResultFormatter for HumanFormatter {
+    fn on_result(&self, r: &EvalRunResult) -> Option<String> {
+        let name = if self.verbose {
+            format!("{} ({}ms)", r.name, r.duration.as_millis())
+        } else {
+            r.name.clone()
+        };
+        let mut out = match &r.result {
+            EvalResult::Pass => {
+                if self.failures_only {
+                    return None;
+                }
+                let mut line = format!("{} {} - {}", "PASS".green().bold(), r.id, name);
+                if let Some(attempt) = r.flaky() {
+                    let note = format!("(flaky: passed on attempt {})", attempt);
+                    line.push_str(&format!(" {}", note.yellow()));
+                }
+                if let Some(confidence) = r.low_confidence() {
+                    let note = format!("(low judge confidence {:.2})", confidence);
+                    line.push_str(&format!(" {}", note.yellow()));
+                }
+                line
+            }
+            EvalResult::Fail(reason) => format!(
+                "{} {} - {}\n       {}",
+                "FAIL".red().bold(),
+                r.id,
+                name,
+                reason.dimmed()
+            ),
+            EvalResult::Skip(reason) => {
+                if self.failures_only {
+                    return None;
+                }
+                format!(
+                    "{} {} - {}\n       {}",
+                    "SKIP".yellow().bold(),
+                    r.id,
+                    name,
+                    reason.dimmed()
+                )
+            }
+            EvalResult::Error(reason) => format!(
+                "{} {} - {}\n       {}",
+                "ERROR".magenta().bold(),
+                r.id,
+                name,
+                reason.dimmed()
+            ),
+        };
+
+        if let (true, Some(artifacts)) = (self.verbose, &r.artifacts) {
+            if !artifacts.tool_calls.is_empty() {
+                out.push_str(&format!(
+                    "\n  tool calls:\n{}",
+                    format_tool_trace(&artifacts.tool_calls)
+                ));
+            }
+            if !artifacts.loop_errors.is_empty() {
+                out.push_str(&format!(
+                    "\n  loop errors:\n    {}",
+                    artifacts.loop_errors.join("\n    ")
+                ));
+            }
+            if let (Some(latency), Some(attempts)) =
+                (artifacts.judge_latency_ms, artifacts.judge_attempts)
+            {
+                out.push_str(&format!(
+                    "\n  judge: {}ms over {} attempt(s)",
+                    latency, attempts
+                ));
+            }
+            if let Some(tokens) = artifacts.total_tokens {
+                out.push_str(&format!("\n  tokens: {}", tokens));
+            }
+            if let Some(score) = artifacts.score {
+                out.push_str(&format!("\n  score: {:.2}", score));
+                for goal in &artifacts.subgoals {
+                    let mark = if goal.passed { "pass" } else { "fail" };
+                    out.push_str(&format!(
+                        "\n    [{}] (w={}) {}",
+                        mark, goal.weight, goal.criteria
+                    ));
+                }
+            }
+            for block in &artifacts.thinking {
+                out.push_str(&format!("\n  thinking:\n{}", block.dimmed()));
+            }
+            for exchange in &artifacts.judge_exchanges {
+                out.push_str(&format!(
+                    "\n  judge ({}) prompt:\n{}\n  judge response:\n{}",
+                    exchange.model, exchange.prompt, exchange.response
+                ));
+            }
+        }
+
+        Some(out)
+    }
+
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        let mut rows: Vec<(String, Tally)> = Vec::new();
+        for category in categories(results) {
+            let suite = results.iter().filter(|r| r.category == category);
+            rows.push((category.to_string(), tally(suite)));
+        }
+        rows.push(("TOTAL".to_string(), tally(results)));
+        let mut out = summary_table("Category", &rows);
+
+        // Attribute results to their source file when the run spans several
+        let mut files: Vec<&str> = Vec::new();
+        for r in results {
+            if !files.contains(&r.file.as_str()) {
+                files.push(&r.file);
+            }
+        }
+        if files.len() > 1 {
+            let rows: Vec<(String, Tally)> = files
+                .iter()
+                .map(|file| {
+                    let suite = results.iter().filter(|r| r.file == *file);
+                    (file.to_string(), tally(suite))
+                })
+                .collect();
+            out.push_str(&summary_table("File", &rows));
+        }
+
+        let flaky: Vec<&str> = results
+            .iter()
+            .filter(|r| r.flaky().is_some())
+            .map(|r| r.id.as_str())
+            .collect();
+        if !flaky.is_empty() {
+            out.push_str(&format!(
+                "\n{}: {} ({})\n",
+                "Flaky".yellow().bold(),
+                flaky.len(),
+                flaky.join(", ")
+            ));
+        }
+
+        let unsure: Vec<&str> = results
+            .iter()
+            .filter(|r| r.low_confidence().is_some())
+            .map(|r| r.id.as_str())
+            .collect();
+        if !unsure.is_empty() {
+            out.push_str(&format!(
+                "\n{}: {}\n",
+                "Low-confidence passes".yellow().bold(),
+                unsure.join(", ")
+            ));
+        }
+
+        let providers: Vec<String> = provider_usage()
+            .iter()
+            .map(|(name, p)| {
+                let count = format!("{} {} ({} judge)", name, p.requests, p.judge);
+                if p.requests == 0 {
+                    count.dimmed().to_string()
+                } else {
+                    count
+                }
+            })
+            .collect();
+        out.push_str(&format!(
+            "\n{}: {}\n",
+            "Providers".bold(),
+            providers.join(", ")
+        ));
+
+        let models = model_usage();
+        if !models.is_empty() {
+            out.push_str(&format!("{}:\n", "Tokens".bold()));
+            let mut total_cost = None;
+            for m in &models {
+                let tokens = if m.input_tokens + m.output_tokens == 0 {
+                    "n/a".to_string()
+                } else {
+                    format!(
+                        "{} in / {} out",
+                        thousands(m.input_tokens),
+                        thousands(m.output_tokens)
+                    )
+                };
+                let cost = match m.cost_usd {
+                    Some(cost) => {
+                        *total_cost.get_or_insert(0.0) += cost;
+                        format!("~${:.4}", cost)
+                    }
+                    None => "cost n/a".to_string(),
+                };
+                out.push_str(&format!(
+                    "  {} {}: {} ({})\n",
+                    m.provider, m.model, tokens, cost
+                ));
+            }
+            if let Some(total) = total_cost {
+                out.push_str(&format!("  estimated total: ~${:.4}\n", total));
+            }
+        }
+        out
+    }
+}
+
+/// Format a count with comma thousands separators, e.g. 12,430
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Aligned pass/fail table with one row per group, colored by health
+fn summary_table(label: &str, rows: &[(String, Tally)]) -> String {
+    let cells: Vec<[String; 6]> = rows
+        .iter()
+        .map(|(name, t)| {
+            [
+                name.clone(),
+                t.passed.to_string(),
+                t.failed.to_string(),
+                t.skipped.to_string(),
+                t.errored.to_string(),
+                pass_rate(t),
+            ]
+        })
+        .collect();
+    let headers = [label, "Passed", "Failed", "Skipped", "Errors", "Pass%"];
+    let mut widths = headers.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    // Pad before coloring so escape codes don't throw off the alignment
+    let line = |row: &[String; 6]| -> Vec<String> {
+        row.iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if i == 0 {
+                    format!("{:<width$}", cell)
+                } else {
+                    format!("{:>width$}", cell)
+                }
+            })
+            .collect()
+    };
+
+    let mut out = format!("\n{}\n", line(&headers.map(String::from)).join("  ").bold());
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    out.push_str(&format!("{}\n", rule.join("  ").dimmed()));
+    for ((name, t), row) in rows.iter().zip(&cells) {
+        let mut padded = line(row);
+        padded[0] = if t.failed > 0 || t.errored > 0 {
+            padded[0].red().to_string()
+        } else if t.passed > 0 {
+            padded[0].green().to_string()
+        } else {
+            padded[0].yellow().to_string()
+        };
+        if t.failed > 0 {
+            padded[2] = padded[2].red().to_string();
+        }
+        if t.skipped > 0 {
+            padded[3] = padded[3].yellow().to_string();
+        }
+        if t.errored > 0 {
+            padded[4] = padded[4].magenta().to_string();
+        }
+        let text = padded.join("  ");
+        if name == "TOTAL" {
+            out.push_str(&format!("{}\n", text.bold()));
+        } else {
+            out.push_str(&format!("{}\n", text));
+        }
+    }
+    out
+}
+
+/// Passed as a share of evals that ran to a verdict; skips and errors don't count against it
+fn pass_rate(t: &Tally) -> String {
+    let decided = t.passed + t.failed;
+    if decided == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.0}%", 100.0 * t.passed as f64 / decided as f64)
+    }
+}
+
+// ============================================================================
+// JSON - The report shape consumed by run-all.py
+// ============================================================================
+
+/// Id, name, category, status and reason of one eval; built with `EvalResult::outcome`
+#[derive(Serialize)]
+pub struct EvalOutcome<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    pub category: &'a str,
+    /// `pass`, `fail`, `skip` or `error`
+    pub status: &'a str,
+    pub reason: Option<&'a str>,
+}
+
+/// JSON output format for each eval result: its outcome plus run details
+#[derive(Serialize)]
+struct JsonEvalResult<'a> {
+    #[serde(flatten)]
+    outcome: EvalOutcome<'a>,
+    file: &'a str,
+    duration_ms: u64,
+    /// Set when the eval needed more than one run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifacts: Option<&'a EvalArtifacts>,
+}
+
+/// JSON output format for the full report
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    runner: &'a str,
+    results: Vec<JsonEvalResult<'a>>,
+    summary: JsonSummary,
+    providers: BTreeMap<String, ProviderRequests>,
+    tokens: Vec<ModelUsage>,
+}
+
+#[derive(Serialize)]
+struct JsonSummary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    errors: usize,
+    total: usize,
+    /// Passes the judge reported less than LOW_CONFIDENCE_PASS confidence in
+    low_confidence_passes: usize,
+    /// Passes that needed a --retries attempt
+    flaky: usize,
+}
+
+impl JsonSummary {
+    fn of<'a>(results: impl IntoIterator<Item = &'a EvalRunResult>) -> Self {
+        let results: Vec<&EvalRunResult> = results.into_iter().collect();
+        let t = tally(results.iter().copied());
+        Self {
+            passed: t.passed,
+            failed: t.failed,
+            skipped: t.skipped,
+            errors: t.errored,
+            total: results.len(),
+            low_confidence_passes: results
+                .iter()
+                .filter(|r| r.low_confidence().is_some())
+                .count(),
+            flaky: results.iter().filter(|r| r.flaky().is_some()).count(),
+        }
+    }
+}
+
+struct JsonFormatter;
+
+impl ResultFormatter for JsonFormatter {
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        let report = JsonReport {
+            runner: "rust",
+            results: results
+                .iter()
+                .map(|r| JsonEvalResult {
+                    outcome: r.result.outcome(&r.id, &r.name, &r.category),
+                    file: &r.file,
+                    duration_ms: r.duration.as_millis() as u64,
+                    attempts: (r.attempts > 1).then_some(r.attempts),
+                    artifacts: r.artifacts.as_ref(),
+                })
+                .collect(),
+            summary: JsonSummary::of(results),
+            providers: provider_usage(),
+            tokens: model_usage(),
+        };
+        serde_json::to_string_pretty(&report).expect("JSON report serializes")
+    }
+}
+
+// ============================================================================
+// Summary File - Aggregate counts written by --summary-json for CI bots
+// ============================================================================
+
+#[derive(Serialize)]
+struct SummaryFile<'a> {
+    runner: &'a str,
+    #[serde(flatten)]
+    totals: JsonSummary,
+    /// Wall-clock time of the whole run, loading included
+    duration_ms: u64,
+    /// --seed to pass to reproduce the run
+    seed: u64,
+    categories: BTreeMap<&'a str, JsonSummary>,
+    /// Ids of evals that failed or errored, in report order
+    failing: Vec<&'a str>,
+}
+
+/// Totals, per-category counts, failing eval ids and the run's seed as pretty-printed JSON
+pub fn render_summary(results: &[EvalRunResult], elapsed: Duration, seed: u64) -> String {
+    let summary = SummaryFile {
+        runner: "rust",
+        totals: JsonSummary::of(results),
+        duration_ms: elapsed.as_millis() as u64,
+        seed,
+        categories: categories(results)
+            .into_iter()
+            .map(|category| {
+                let suite = results.iter().filter(|r| r.category == category);
+                (category, JsonSummary::of(suite))
+            })
+            .collect(),
+        failing: results
+            .iter()
+            .filter(|r| r.result.is_failure())
+            .map(|r| r.id.as_str())
+            .collect(),
+    };
+    serde_json::to_string_pretty(&summary).expect("JSON summary serializes")
+}
+
+// ============================================================================
+// JUnit XML - One testsuite per category
+// ============================================================================
+
+struct JunitFormatter;
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Summed wall-clock seconds, for JUnit `time` attributes
+fn total_secs<'a>(results: impl Iterator<Item = &'a EvalRunResult>) -> f64 {
+    results.map(|r| r.duration.as_secs_f64()).sum()
+}
+
+impl ResultFormatter for JunitFormatter {
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        let t = tally(results);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"mux-evals\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            results.len(),
+            t.failed,
+            t.errored,
+            t.skipped,
+            total_secs(results.iter())
+        ));
+
+        for category in categories(results) {
+            let suite: Vec<&EvalRunResult> =
+                results.iter().filter(|r| r.category == category).collect();
+            let counts = tally(suite.iter().copied());
+
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+                xml_escape(category),
+                suite.len(),
+                counts.failed,
+                counts.errored,
+                counts.skipped,
+                total_secs(suite.iter().copied())
+            ));
+            for r in suite {
+                let open = format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                    xml_escape(&r.category),
+                    xml_escape(&r.id),
+                    r.duration.as_secs_f64()
+                );
+                match &r.result {
+                    EvalResult::Pass => xml.push_str(&format!("{}/>\n", open)),
+                    EvalResult::Fail(reason) => xml.push_str(&format!(
+                        "{}>\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                        open,
+                        xml_escape(reason)
+                    )),
+                    EvalResult::Skip(reason) => xml.push_str(&format!(
+                        "{}>\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                        open,
+                        xml_escape(reason)
+                    )),
+                    EvalResult::Error(reason) => xml.push_str(&format!(
+                        "{}>\n      <error message=\"{}\"/>\n    </testcase>\n",
+                        open,
+                        xml_escape(reason)
+                    )),
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+
+        xml.push_str("</testsuites>");
+        xml
+    }
+}
+
+// ============================================================================
+// Markdown - A table suitable for PR comments
+// ============================================================================
+
+struct MarkdownFormatter;
+
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+impl ResultFormatter for MarkdownFormatter {
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        let t = tally(results);
+        let mut md = String::from(
+            "| ID | Name | Category | Status | Reason |\n|----|------|----------|--------|--------|\n",
+        );
+        for r in results {
+            let status = match r.low_confidence() {
+                Some(confidence) => format!("pass (low confidence {:.2})", confidence),
+                None => r.status().to_string(),
+            };
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                markdown_cell(&r.id),
+                markdown_cell(&r.name),
+                markdown_cell(&r.category),
+                status,
+                markdown_cell(r.reason().unwrap_or(""))
+            ));
+        }
+
+        // Per-category counts make it obvious which area is failing, as in the human summary
+        let categories = categories(results);
+        if categories.len() > 1 {
+            md.push_str(
+                "\n| Category | Passed | Failed | Skipped | Errors |\n|----------|--------|--------|---------|--------|\n",
+            );
+            for category in categories {
+                let c = tally(results.iter().filter(|r| r.category == category));
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    markdown_cell(category),
+                    c.passed,
+                    c.failed,
+                    c.skipped,
+                    c.errored
+                ));
+            }
+        }
+        md.push_str(&format!(
+            "\n**Results**: {} passed, {} failed, {} skipped, {} errored",
+            t.passed, t.failed, t.skipped, t.errored
+        ));
+        md
+    }
+}
+
+// ============================================================================
+// CSV - One row per eval
+// ============================================================================
+
+struct CsvFormatter;
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl ResultFormatter for CsvFormatter {
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        // One column per metric reported by any eval; evals that didn't measure it leave it blank
+        let mut metrics: Vec<&str> = results
+            .iter()
+            .filter_map(|r| r.artifacts.as_ref())
+            .flat_map(|a| a.metrics.keys().map(String::as_str))
+            .collect();
+        metrics.sort_unstable();
+        metrics.dedup();
+
+        let mut csv = String::from("id,name,category,status,reason");
+        for name in &metrics {
+            csv.push_str(&format!(",{}", csv_field(name)));
+        }
+        for r in results {
+            csv.push_str(&format!(
+                "\n{},{},{},{},{}",
+                csv_field(&r.id),
+                csv_field(&r.name),
+                csv_field(&r.category),
+                r.status(),
+                csv_field(r.reason().unwrap_or(""))
+            ));
+            for name in &metrics {
+                let value = r
+                    .artifacts
+                    .as_ref()
+                    .and_then(|a| a.metrics.get(*name))
+                    .map(|v| v.to_string())
+                    .unwrap_or_default();
+                csv.push_str(&format!(",{}", value));
+            }
+        }
+        csv
+    }
+}
+
+// ============================================================================
+// TAP - Test Anything Protocol version 13 stream
+// ============================================================================
+
+struct TapFormatter;
+
+/// A test point description; `#` would otherwise start a directive
+fn tap_description(r: &EvalRunResult) -> String {
+    format!("{} {}", r.id, r.name)
+        .replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace('\n', " ")
+}
+
+impl ResultFormatter for TapFormatter {
+    fn render(&self, results: &[EvalRunResult]) -> String {
+        let mut tap = format!("TAP version 13\n1..{}", results.len());
+        for (i, r) in results.iter().enumerate() {
+            let n = i + 1;
+            let description = tap_description(r);
+            match &r.result {
+                EvalResult::Pass => tap.push_str(&format!("\nok {} - {}", n, description)),
+                EvalResult::Skip(reason) => tap.push_str(&format!(
+                    "\nok {} - {} # SKIP {}",
+                    n,
+                    description,
+                    reason.replace('\n', " ")
+                )),
+                // Errors fail the run too, so they are `not ok` with their own severity
+                EvalResult::Fail(reason) | EvalResult::Error(reason) => {
+                    let severity = if matches!(r.result, EvalResult::Error(_)) {
+                        "error"
+                    } else {
+                        "fail"
+                    };
+                    // JSON strings are valid YAML double-quoted scalars
+                    let quote = |s: &str| serde_json::to_string(s).expect("string serializes");
+                    tap.push_str(&format!(
+                        "\nnot ok {} - {}\n  ---\n  message: {}\n  severity: {}\n  category: {}\n  duration_ms: {}\n  ...",
+                        n,
+                        description,
+                        quote(reason),
+                        severity,
+                        quote(&r.category),
+                        r.duration.as_millis()
+                    ));
+                }
+            }
+        }
+        tap
+    }
+}
@@ -0,0 +1,434 @@
+// ABOUTME: Builtin test tools that tool, agent and hook evals can register by name.
+// ABOUTME: build_registry assembles a mux Registry from those names, e.g. `"tools": ["add", "divide"]`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mux::tool::{Registry, Tool, ToolResult};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Names accepted by `build_registry`, in the order they're documented
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "add",
+    "divide",
+    "multiply",
+    "greet",
+    "echo",
+    "get_info",
+    "counter",
+    "fetch_report",
+    "shell",
+    "fetch",
+];
+
+/// Programs the shell tool may run; anything else is refused before a process is spawned
+const SHELL_ALLOWLIST: &[&str] = &[
+    "echo", "printf", "pwd", "ls", "cat", "date", "true", "false",
+];
+
+/// How long a shell command may run before it is killed and the call fails
+const SHELL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a fetch may take, connect to last byte, unless the call passes `timeout_ms`
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bytes of a fetched body kept in the result unless the call passes `max_bytes`
+const FETCH_MAX_BYTES: usize = 16 * 1024;
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTIN_TOOLS.contains(&name)
+}
+
+/// A registry holding the named builtin tools; an unknown name is an error
+///
+/// Stateful tools such as `counter` start fresh in every registry.
+pub async fn build_registry(names: &[&str]) -> Result<Registry> {
+    let registry = Registry::new();
+    for name in names {
+        match *name {
+            "add" => registry.register(AddTool).await,
+            "divide" => registry.register(DivideTool).await,
+            "multiply" => registry.register(MultiplyTool).await,
+            "greet" => registry.register(GreetTool).await,
+            "echo" => registry.register(EchoTool).await,
+            "get_info" => registry.register(GetInfoTool).await,
+            "counter" => registry.register(CounterTool::new()).await,
+            "fetch_report" => registry.register(AlwaysErrorTool).await,
+            "shell" => registry.register(ShellTool).await,
+            "fetch" => registry.register(FetchTool::new()).await,
+            other => anyhow::bail!(
+                "unknown builtin tool '{}' (known: {})",
+                other,
+                BUILTIN_TOOLS.join(", ")
+            ),
+        }
+    }
+    Ok(registry)
+}
+
+/// Schema shared by the two-operand arithmetic tools
+fn operands_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "a": {"type": "number"},
+            "b": {"type": "number"}
+        },
+        "required": ["a", "b"]
+    })
+}
+
+struct AddTool;
+
+#[async_trait]
+impl Tool for AddTool {
+    fn name(&self) -> &str {
+        "add"
+    }
+    fn description(&self) -> &str {
+        "Adds two numbers"
+    }
+    fn schema(&self) -> serde_json::Value {
+        operands_schema()
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let a = params["a"].as_f64().unwrap_or(0.0);
+        let b = params["b"].as_f64().unwrap_or(0.0);
+        Ok(ToolResult::text(format!("{}", a + b)))
+    }
+}
+
+struct DivideTool;
+
+#[async_trait]
+impl Tool for DivideTool {
+    fn name(&self) -> &str {
+        "divide"
+    }
+    fn description(&self) -> &str {
+        "Divides two numbers"
+    }
+    fn schema(&self) -> serde_json::Value {
+        operands_schema()
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let a = params["a"].as_f64().unwrap_or(0.0);
+        let b = params["b"].as_f64().unwrap_or(0.0);
+        if b == 0.0 {
+            return Err(anyhow::anyhow!("Division by zero"));
+        }
+        Ok(ToolResult::text(format!("{}", a / b)))
+    }
+}
+
+struct MultiplyTool;
+
+#[async_trait]
+impl Tool for MultiplyTool {
+    fn name(&self) -> &str {
+        "multiply"
+    }
+    fn description(&self) -> &str {
+        "Multiplies two numbers"
+    }
+    fn schema(&self) -> serde_json::Value {
+        operands_schema()
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let a = params["a"].as_f64().unwrap_or(0.0);
+        let b = params["b"].as_f64().unwrap_or(0.0);
+        Ok(ToolResult::text(format!("{}", a * b)))
+    }
+}
+
+struct GreetTool;
+
+#[async_trait]
+impl Tool for GreetTool {
+    fn name(&self) -> &str {
+        "greet"
+    }
+    fn description(&self) -> &str {
+        "Returns greeting"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"}
+            },
+            "required": ["name"]
+        })
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let name = params["name"].as_str().unwrap_or("World");
+        Ok(ToolResult::text(format!("Hello, {}!", name)))
+    }
+}
+
+struct EchoTool;
+
+#[async_trait]
+impl Tool for EchoTool {
+    fn name(&self) -> &str {
+        "echo"
+    }
+    fn description(&self) -> &str {
+        "Returns its text unchanged"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string"}
+            },
+            "required": ["text"]
+        })
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let text = params["text"].as_str().unwrap_or("");
+        Ok(ToolResult::text(text.to_string()))
+    }
+}
+
+struct GetInfoTool;
+
+#[async_trait]
+impl Tool for GetInfoTool {
+    fn name(&self) -> &str {
+        "get_info"
+    }
+    fn description(&self) -> &str {
+        "Returns info object"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({"type": "object", "properties": {}})
+    }
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        Ok(ToolResult::text(
+            serde_json::json!({"version": "1.0", "name": "mux"}).to_string(),
+        ))
+    }
+}
+
+struct CounterTool {
+    count: AtomicUsize,
+}
+
+impl CounterTool {
+    fn new() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CounterTool {
+    fn name(&self) -> &str {
+        "counter"
+    }
+    fn description(&self) -> &str {
+        "Increments counter"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({"type": "object", "properties": {}})
+    }
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let new_count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(ToolResult::text(format!("Count: {}", new_count)))
+    }
+}
+
+struct AlwaysErrorTool;
+
+#[async_trait]
+impl Tool for AlwaysErrorTool {
+    fn name(&self) -> &str {
+        "fetch_report"
+    }
+    fn description(&self) -> &str {
+        "Fetches today's report"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({"type": "object", "properties": {}})
+    }
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "Disk quota exceeded while reading /var/reports"
+        ))
+    }
+}
+
+/// Runs an allowlisted command and returns its output; a nonzero exit is a tool error
+///
+/// The command is split on whitespace and run directly, without a shell, so pipes, globs and
+/// redirections are passed through as literal arguments. It runs in an empty scratch dir, and
+/// arguments may not name absolute paths or `..`, so a model can't make `cat` or `ls` read
+/// the runner's files (keys in `.env`, `~/.ssh`).
+struct ShellTool;
+
+#[async_trait]
+impl Tool for ShellTool {
+    fn name(&self) -> &str {
+        "shell"
+    }
+    fn description(&self) -> &str {
+        "Runs a shell command and returns its output"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "command": {"type": "string"}
+            },
+            "required": ["command"]
+        })
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let command = params["command"].as_str().unwrap_or("").trim();
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No command given"))?;
+        if !SHELL_ALLOWLIST.contains(&program) {
+            anyhow::bail!(
+                "Command '{}' is not allowed (allowed: {})",
+                program,
+                SHELL_ALLOWLIST.join(", ")
+            );
+        }
+
+        let args: Vec<&str> = words.collect();
+        if let Some(arg) = args.iter().find(|a| escapes_scratch_dir(a)) {
+            anyhow::bail!(
+                "Argument '{}' is not allowed: paths must stay inside the scratch dir",
+                arg
+            );
+        }
+
+        let scratch = tempfile::tempdir()?;
+        let output = tokio::process::Command::new(program)
+            .args(args)
+            .current_dir(scratch.path())
+            .kill_on_drop(true)
+            .output();
+        let output = match tokio::time::timeout(SHELL_TIMEOUT, output).await {
+            Ok(output) => output?,
+            Err(_) => anyhow::bail!("'{}' timed out after {}s", command, SHELL_TIMEOUT.as_secs()),
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            match stderr.trim() {
+                "" => anyhow::bail!("'{}' exited with {}", command, output.status),
+                stderr => anyhow::bail!("'{}' exited with {}: {}", command, output.status, stderr),
+            }
+        }
+        let mut text = stdout.into_owned();
+        if !stderr.trim().is_empty() {
+            text.push_str(&format!("\nstderr: {}", stderr.trim()));
+        }
+        Ok(ToolResult::text(text))
+    }
+}
+
+/// Whether `arg` could reach outside the working dir: an absolute or `~` path or a `..` component,
+/// also after an `=` as in `--option=/etc/passwd`
+fn escapes_scratch_dir(arg: &str) -> bool {
+    arg.split('=').any(|part| {
+        part.starts_with('/') || part.starts_with('~') || part.split('/').any(|c| c == "..")
+    })
+}
+
+/// GETs a URL and returns the body, cut to `max_bytes`; a non-2xx status is a tool error
+struct FetchTool {
+    http: reqwest::Client,
+}
+
+impl FetchTool {
+    fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for FetchTool {
+    fn name(&self) -> &str {
+        "fetch"
+    }
+    fn description(&self) -> &str {
+        "Fetches a URL with GET and returns the response body"
+    }
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string"},
+                "max_bytes": {"type": "integer", "minimum": 1},
+                "timeout_ms": {"type": "integer", "minimum": 1}
+            },
+            "required": ["url"]
+        })
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let url = params["url"].as_str().unwrap_or("").trim();
+        if url.is_empty() {
+            anyhow::bail!("No url given");
+        }
+        // reqwest would refuse other schemes too, but with an opaque builder error
+        match url.split_once("://") {
+            Some((scheme, _))
+                if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") => {}
+            _ => anyhow::bail!("Only http and https URLs can be fetched, got {}", url),
+        }
+        let max_bytes = params["max_bytes"]
+            .as_u64()
+            .map_or(FETCH_MAX_BYTES, |n| n as usize);
+        let timeout = params["timeout_ms"]
+            .as_u64()
+            .map_or(FETCH_TIMEOUT, Duration::from_millis);
+
+        let reply = self
+            .http
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| fetch_error(url, timeout, e))?;
+        let status = reply.status();
+        let body = reply
+            .bytes()
+            .await
+            .map_err(|e| fetch_error(url, timeout, e))?;
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&body[..body.len().min(max_bytes)]);
+            match body.trim() {
+                "" => anyhow::bail!("GET {} returned {}", url, status),
+                body => anyhow::bail!("GET {} returned {}: {}", url, status, body),
+            }
+        }
+
+        let mut text = String::from_utf8_lossy(&body[..body.len().min(max_bytes)]).into_owned();
+        if body.len() > max_bytes {
+            text.push_str(&format!(
+                "\n[truncated to {} of {} bytes]",
+                max_bytes,
+                body.len()
+            ));
+        }
+        Ok(ToolResult::text(text))
+    }
+}
+
+/// A transport failure, worded so a timeout reads as one rather than a generic send error
+fn fetch_error(url: &str, timeout: Duration, e: reqwest::Error) -> anyhow::Error {
+    if e.is_timeout() {
+        anyhow::anyhow!("GET {} timed out after {}ms", url, timeout.as_millis())
+    } else {
+        anyhow::anyhow!("GET {} failed: {}", url, e)
+    }
+}
@@ -0,0 +1,101 @@
+// ABOUTME: Optional per-eval dump of every LLM request and response, judge calls included.
+// ABOUTME: Enabled with --trace-dir; write failures only warn, so tracing never changes a result.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use mux::llm::{Request, Response};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Directory from --trace-dir; tracing is off while unset
+static TRACE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Calls recorded per eval id, waiting for the eval to finish
+static PENDING: Mutex<BTreeMap<String, Vec<Value>>> = Mutex::new(BTreeMap::new());
+
+/// Eval ids whose trace file was already written this run
+static WRITTEN: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Turn tracing on, creating the directory up front so a bad path fails the run early
+pub fn enable(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create trace dir {}", dir.display()))?;
+    let _ = TRACE_DIR.set(dir.to_path_buf());
+    Ok(())
+}
+
+/// Record one call for the eval being run; `outcome` is the response or the error message
+pub fn record(provider: &str, judge: bool, request: &Request, outcome: Result<&Response, String>) {
+    if TRACE_DIR.get().is_none() {
+        return;
+    }
+
+    let tools: Vec<&str> = request.tools.iter().map(|t| t.name.as_str()).collect();
+    let (response, error) = match outcome {
+        Ok(response) => (serde_json::to_value(response).ok(), None),
+        Err(e) => (None, Some(e)),
+    };
+    let entry = json!({
+        "provider": provider,
+        "kind": if judge { "judge" } else { "provider" },
+        "request": {
+            "model": request.model,
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature,
+            "tools": tools,
+            "messages": request.messages,
+        },
+        "response": response,
+        "error": error,
+    });
+
+    PENDING
+        .lock()
+        .unwrap()
+        .entry(crate::current_eval_id())
+        .or_default()
+        .push(entry);
+}
+
+/// Write an eval's recorded calls to `<trace-dir>/<eval-id>.json`
+///
+/// A retried eval adds to the file it wrote earlier in the run, so failing attempts stay
+/// visible next to the one that passed.
+pub fn flush(eval_id: &str) {
+    let dir = match TRACE_DIR.get() {
+        Some(dir) => dir,
+        None => return,
+    };
+    let entries = PENDING.lock().unwrap().remove(eval_id).unwrap_or_default();
+    if entries.is_empty() {
+        return;
+    }
+
+    let path = dir.join(format!("{}.json", crate::eval_file_name(eval_id)));
+    let first_write = WRITTEN.lock().unwrap().insert(eval_id.to_string());
+    if let Err(e) = write(&path, entries, !first_write) {
+        eprintln!(
+            "{} failed to write trace for {}: {:#}",
+            "warning:".yellow().bold(),
+            eval_id,
+            e
+        );
+    }
+}
+
+fn write(path: &Path, entries: Vec<Value>, append: bool) -> Result<()> {
+    let mut calls: Vec<Value> = if append && path.exists() {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid trace {}", path.display()))?
+    } else {
+        Vec::new()
+    };
+    calls.extend(entries);
+
+    let json = serde_json::to_string_pretty(&calls)?;
+    std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+}